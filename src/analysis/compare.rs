@@ -1,14 +1,58 @@
-use super::executor::ExecutionResult;
+use super::executor::{CapturedOutput, ExecutionResult};
+use super::float::{FloatTolerance, StdoutMode, TokenMismatch};
+use super::normalize::{normalize, Normalizer};
 use super::result::{AnalysisResult, Diff};
+use super::stderr::{extract_panic, StderrMode};
 
+/// Compares the executions of two binaries, the left one built by the
+/// compiler under test and the right one by the compiler it is checked
+/// against.
 pub struct Comparison {
-    gccrs: ExecutionResult,
-    rustc: ExecutionResult,
+    compilers: [String; 2],
+    left: ExecutionResult,
+    right: ExecutionResult,
+    options: CompareOptions,
+}
+
+/// Settings that change how the two executions are compared.
+#[derive(Debug, Default, Clone)]
+pub struct CompareOptions {
+    /// Golden output both executions are checked against, on top of being
+    /// compared with each other.
+    pub expected: Option<Expected>,
+
+    /// Applied to stdout of both sides, and to the golden stdout, before
+    /// comparing. Differences are still displayed with the raw output.
+    pub normalizers: Vec<Normalizer>,
+
+    pub stdout_mode: StdoutMode,
+
+    pub stderr_mode: StderrMode,
+
+    /// Both binaries were built from files with the same lines, so a panic
+    /// has to happen at the same `line:column` on both sides.
+    pub same_layout: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Expected {
+    pub stdout: Option<Vec<u8>>,
+    pub exit_code: Option<i32>,
 }
 
 impl Comparison {
-    pub fn new(gccrs: ExecutionResult, rustc: ExecutionResult) -> Self {
-        Comparison { gccrs, rustc }
+    pub fn new(
+        compilers: [String; 2],
+        left: ExecutionResult,
+        right: ExecutionResult,
+        options: CompareOptions,
+    ) -> Self {
+        Comparison {
+            compilers,
+            left,
+            right,
+            options,
+        }
     }
 
     pub fn compare(&self) -> AnalysisResult {
@@ -16,90 +60,243 @@ impl Comparison {
 
         if let Some(timeout_diff) = self.compare_timeouts() {
             differences.push(Diff::Timeout(timeout_diff.0, timeout_diff.1));
-            return AnalysisResult { differences }; // Skip other section if we timeout
+            return self.result(differences); // Skip other section if we timeout
         }
 
-        if let Some(exit_code_diff) = self.compare_exit_code() {
-            differences.push(Diff::ExitCode(exit_code_diff.0, exit_code_diff.1));
+        if !self.left.termination.is_equivalent(&self.right.termination) {
+            differences.push(Diff::ExitCode(
+                self.left.termination.clone(),
+                self.right.termination.clone(),
+            ));
         }
 
-        if let Some(stdout_diff) = self.compare_output(
-            &self.gccrs.output.as_ref().map(|o| (&o.stdout)),
-            &self.rustc.output.as_ref().map(|o| (&o.stdout)),
-        ) {
-            differences.push(Diff::Stdout(stdout_diff.0, stdout_diff.1));
+        let left_stdout = self.left.output.as_ref().map(|o| &o.stdout);
+        let right_stdout = self.right.output.as_ref().map(|o| &o.stdout);
+        match self.options.stdout_mode {
+            StdoutMode::Exact => {
+                if let Some(stdout_diff) = self.compare_output(&left_stdout, &right_stdout) {
+                    differences.push(Diff::Stdout(stdout_diff.0, stdout_diff.1));
+                }
+            }
+            StdoutMode::Float(tolerance) => {
+                if let Some(mismatches) =
+                    self.compare_tokens(tolerance, &left_stdout, &right_stdout)
+                {
+                    differences.push(Diff::StdoutTokens(mismatches));
+                }
+            }
         }
 
         if let Some(stderr_diff) = self.compare_stderr() {
             differences.push(Diff::Stderr(stderr_diff.0, stderr_diff.1));
         }
 
-        AnalysisResult { differences }
+        if let Some(expected) = &self.options.expected {
+            self.compare_expected(expected, &mut differences);
+        }
+
+        self.result(differences)
     }
 
-    /// Check only if error exists or not but we dont compare
-    /// the error messages as error messages may differ.
-    fn compare_stderr(&self) -> Option<(String, String)> {
-        let gccrs_stderr = self.gccrs.output.as_ref().map(|o| &o.stderr);
-        let rustc_stderr = self.rustc.output.as_ref().map(|o| &o.stderr);
+    fn result(&self, differences: Vec<Diff>) -> AnalysisResult {
+        AnalysisResult {
+            differences,
+            compilers: self.compilers.clone(),
+        }
+    }
 
-        let gccrs_has_error = gccrs_stderr.map_or(false, |stderr| !stderr.is_empty());
-        let rustc_has_error = rustc_stderr.map_or(false, |stderr| !stderr.is_empty());
+    /// Checks each side against the golden output so that a difference can
+    /// be pinned on one compiler, or on both when they agree but are wrong.
+    fn compare_expected(&self, expected: &Expected, differences: &mut Vec<Diff>) {
+        for (compiler, result) in self.compilers.iter().zip([&self.left, &self.right]) {
+            let Some(output) = &result.output else {
+                continue;
+            };
 
-        if gccrs_has_error != rustc_has_error {
-            Some((
-                Self::format_output(&gccrs_stderr),
-                Self::format_output(&rustc_stderr),
-            ))
-        } else {
-            None
+            if let Some(stdout) = &expected.stdout {
+                if !self.stdout_matches(stdout, &output.stdout.bytes) || output.stdout.truncated {
+                    differences.push(Diff::ExpectedStdout {
+                        compiler: compiler.clone(),
+                        expected: String::from_utf8_lossy(stdout).into_owned(),
+                        actual: Self::format_output(&Some(&output.stdout)),
+                    });
+                }
+            }
+
+            if let Some(exit_code) = expected.exit_code {
+                if output.status.code() != Some(exit_code) {
+                    differences.push(Diff::ExpectedExitCode {
+                        compiler: compiler.clone(),
+                        expected: exit_code.to_string(),
+                        actual: result.termination.to_string(),
+                    });
+                }
+            }
         }
     }
 
-    fn compare_timeouts(&self) -> Option<(bool, bool)> {
-        if self.gccrs.timed_out != self.rustc.timed_out {
-            Some((self.gccrs.timed_out, self.rustc.timed_out))
-        } else {
+    fn compare_stderr(&self) -> Option<(String, String)> {
+        let left_stderr = self.left.output.as_ref().map(|o| &o.stderr);
+        let right_stderr = self.right.output.as_ref().map(|o| &o.stderr);
+        let left_text = Self::format_output(&left_stderr);
+        let right_text = Self::format_output(&right_stderr);
+
+        let equal = match self.options.stderr_mode {
+            StderrMode::Ignore => true,
+            // Error messages of the two runtimes differ, so only check
+            // whether each side wrote any.
+            StderrMode::Presence => {
+                let left_has_error = left_stderr.is_some_and(|stderr| !stderr.bytes.is_empty());
+                let right_has_error = right_stderr.is_some_and(|stderr| !stderr.bytes.is_empty());
+                left_has_error == right_has_error
+            }
+            StderrMode::Exact => left_text == right_text,
+            StderrMode::Normalized => {
+                let normalizers = &self.options.normalizers;
+                normalize(&left_text, normalizers) == normalize(&right_text, normalizers)
+            }
+            StderrMode::PanicAware => {
+                match (extract_panic(&left_text), extract_panic(&right_text)) {
+                    (Some(left), Some(right)) => {
+                        left.message == right.message
+                            && (!self.options.same_layout
+                                || left.location.is_none()
+                                || right.location.is_none()
+                                || left.location == right.location)
+                    }
+                    (left, right) => left.is_none() && right.is_none(),
+                }
+            }
+        };
+
+        if equal {
             None
+        } else {
+            Some((left_text, right_text))
         }
     }
 
-    fn format_exit_code(code: Option<i32>) -> String {
-        code.map_or_else(|| "Terminated by signal".to_string(), |c| c.to_string())
-    }
-
-    fn compare_exit_code(&self) -> Option<(String, String)> {
-        let gccrs_exit_code = self.gccrs.output.as_ref().and_then(|o| o.status.code());
-        let rustc_exit_code = self.rustc.output.as_ref().and_then(|o| o.status.code());
-
-        if gccrs_exit_code != rustc_exit_code {
-            Some((
-                Self::format_exit_code(gccrs_exit_code),
-                Self::format_exit_code(rustc_exit_code),
-            ))
+    fn compare_timeouts(&self) -> Option<(bool, bool)> {
+        if self.left.timed_out != self.right.timed_out {
+            Some((self.left.timed_out, self.right.timed_out))
         } else {
             None
         }
     }
 
-    fn format_output(opt_output: &Option<&Vec<u8>>) -> String {
+    fn format_output(opt_output: &Option<&CapturedOutput>) -> String {
         opt_output
-            .map(|output| String::from_utf8_lossy(output).into_owned())
+            .map(|output| {
+                let mut text = String::from_utf8_lossy(&output.bytes).into_owned();
+                if output.truncated {
+                    text.push_str(&format!(
+                        "\n[output truncated after {} bytes]\n",
+                        output.bytes.len()
+                    ));
+                }
+                text
+            })
             .unwrap_or_else(|| "No output (timed out)".to_string())
     }
 
     fn compare_output(
         &self,
-        gccrs: &Option<&Vec<u8>>,
-        rustc: &Option<&Vec<u8>>,
+        left: &Option<&CapturedOutput>,
+        right: &Option<&CapturedOutput>,
     ) -> Option<(String, String)> {
-        let gccrs_output = Self::format_output(gccrs);
-        let rustc_output = Self::format_output(rustc);
+        let left_output = Self::format_output(left);
+        let right_output = Self::format_output(right);
 
-        if gccrs_output != rustc_output {
-            Some((gccrs_output, rustc_output))
+        let normalizers = &self.options.normalizers;
+        if normalize(&left_output, normalizers) != normalize(&right_output, normalizers) {
+            Some((left_output, right_output))
         } else {
             None
         }
     }
+
+    /// Compares numbers in the outputs by value and everything else exactly.
+    fn compare_tokens(
+        &self,
+        tolerance: FloatTolerance,
+        left: &Option<&CapturedOutput>,
+        right: &Option<&CapturedOutput>,
+    ) -> Option<Vec<TokenMismatch>> {
+        let normalizers = &self.options.normalizers;
+        let mismatches = tolerance.compare(
+            &normalize(&Self::format_output(left), normalizers),
+            &normalize(&Self::format_output(right), normalizers),
+        );
+        (!mismatches.is_empty()).then_some(mismatches)
+    }
+
+    fn stdout_matches(&self, expected: &[u8], actual: &[u8]) -> bool {
+        let normalizers = &self.options.normalizers;
+        if normalizers.is_empty() && self.options.stdout_mode == StdoutMode::Exact {
+            return expected == actual;
+        }
+        let expected = normalize(&String::from_utf8_lossy(expected), normalizers);
+        let actual = normalize(&String::from_utf8_lossy(actual), normalizers);
+        match self.options.stdout_mode {
+            StdoutMode::Exact => expected == actual,
+            StdoutMode::Float(tolerance) => tolerance.compare(&actual, &expected).is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{ExecutionOutput, Termination};
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    fn panicked(stderr: &str) -> ExecutionResult {
+        let status = ExitStatus::from_raw(101 << 8);
+        ExecutionResult {
+            output: Some(ExecutionOutput {
+                status,
+                stdout: CapturedOutput::default(),
+                stderr: CapturedOutput {
+                    bytes: stderr.as_bytes().to_vec(),
+                    truncated: false,
+                },
+            }),
+            timed_out: false,
+            termination: Termination::from_status(status),
+        }
+    }
+
+    fn stderr_differs(left: &str, right: &str, same_layout: bool) -> bool {
+        let options = CompareOptions {
+            stderr_mode: StderrMode::PanicAware,
+            same_layout,
+            ..CompareOptions::default()
+        };
+        Comparison::new(
+            ["gccrs".to_string(), "rustc".to_string()],
+            panicked(left),
+            panicked(right),
+            options,
+        )
+        .compare_stderr()
+        .is_some()
+    }
+
+    #[test]
+    fn panic_location_counts_only_with_the_same_layout() {
+        let left = "thread 'main' panicked at gccrs.rs:5:5:\nboom\n";
+        let right = "thread 'main' panicked at rustc.rs:7:5:\nboom\n";
+        assert!(stderr_differs(left, right, true));
+        assert!(!stderr_differs(left, right, false));
+        assert!(!stderr_differs(left, left, true));
+    }
+
+    #[test]
+    fn panic_message_always_counts() {
+        let left = "thread 'main' panicked at a.rs:5:5:\nboom\n";
+        let right = "thread 'main' panicked at a.rs:5:5:\nbang\n";
+        assert!(stderr_differs(left, right, false));
+        assert!(stderr_differs(left, "", false));
+    }
 }
@@ -0,0 +1,165 @@
+use super::normalize::{normalize, Normalizer};
+use super::result::{AnalysisResult, Diff};
+
+/// What a compile-fail test expects from the diagnostics of both compilers,
+/// on top of both of them rejecting the program.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CompileFailExpectation {
+    /// Error codes such as `E0308`, each of which has to appear as
+    /// `[E0308]` in the diagnostics.
+    pub error_codes: Vec<String>,
+    /// Fragments that have to appear in the normalized diagnostics.
+    /// Whitespace runs are collapsed before matching.
+    pub messages: Vec<String>,
+}
+
+impl CompileFailExpectation {
+    /// Returns the expected error codes and messages that `diagnostics` lacks.
+    pub fn missing(&self, diagnostics: &str, normalizers: &[Normalizer]) -> Vec<String> {
+        let normalized = collapse_whitespace(&normalize(diagnostics, normalizers));
+
+        let codes = self
+            .error_codes
+            .iter()
+            .filter(|code| !diagnostics.contains(&format!("[{}]", code)))
+            .map(|code| format!("error code {}", code));
+        let messages = self
+            .messages
+            .iter()
+            .filter(|message| !normalized.contains(&collapse_whitespace(message)))
+            .map(|message| format!("message `{}`", message));
+        codes.chain(messages).collect()
+    }
+}
+
+/// Compares the outcome of building a compile-fail test with the two
+/// `compilers`. `left` and `right` hold the diagnostics of a compiler that
+/// rejected the program, and `None` if it accepted it.
+pub fn compare_rejections(
+    compilers: [&str; 2],
+    left: Option<&str>,
+    right: Option<&str>,
+    expectation: &CompileFailExpectation,
+    normalizers: &[Normalizer],
+) -> AnalysisResult {
+    let mut differences = Vec::new();
+
+    match (left, right) {
+        (Some(left), Some(right)) => {
+            for (compiler, diagnostics) in compilers.into_iter().zip([left, right]) {
+                let missing = expectation.missing(diagnostics, normalizers);
+                if !missing.is_empty() {
+                    differences.push(Diff::ErrorMismatch {
+                        compiler: compiler.to_string(),
+                        missing,
+                        diagnostics: diagnostics.to_string(),
+                    });
+                }
+            }
+        }
+        (left, right) => differences.push(Diff::Rejection {
+            left: left.map(str::to_string),
+            right: right.map(str::to_string),
+        }),
+    }
+
+    AnalysisResult {
+        differences,
+        compilers: compilers.map(str::to_string),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPILERS: [&str; 2] = ["rustc", "gccrs"];
+    const RUSTC: &str = "error[E0308]: mismatched types\n  --> case.rs:2:18\n   |\n2  |     let x: u8 = \"a\";\n   |            --   ^^^ expected `u8`, found `&str`\n";
+    const GCCRS: &str =
+        "case.rs:2:18: error: mismatched types,\n  expected `u8` but got `&str` [E0308]\n";
+
+    fn expectation(error_codes: &[&str], messages: &[&str]) -> CompileFailExpectation {
+        CompileFailExpectation {
+            error_codes: error_codes.iter().map(|code| code.to_string()).collect(),
+            messages: messages.iter().map(|message| message.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn matches_bracketed_error_codes_only() {
+        let expectation = expectation(&["E0308", "E0384"], &[]);
+        assert_eq!(expectation.missing(RUSTC, &[]), ["error code E0384"]);
+        assert_eq!(
+            expectation.missing("error: E0308 and E0384 mentioned", &[]),
+            ["error code E0308", "error code E0384"]
+        );
+    }
+
+    #[test]
+    fn matches_messages_with_collapsed_whitespace() {
+        let expectation = expectation(&[], &["mismatched  types", "expected\n`u8`"]);
+        assert!(expectation.missing(RUSTC, &[]).is_empty());
+        assert_eq!(
+            expectation.missing("error: type mismatch", &[]),
+            ["message `mismatched  types`", "message `expected\n`u8``"]
+        );
+    }
+
+    #[test]
+    fn passes_when_both_reject_as_expected() {
+        let result = compare_rejections(
+            COMPILERS,
+            Some(RUSTC),
+            Some(GCCRS),
+            &expectation(&["E0308"], &["mismatched types"]),
+            &[],
+        );
+        assert!(result.differences.is_empty());
+        assert_eq!(result.compilers, COMPILERS.map(str::to_string));
+    }
+
+    #[test]
+    fn reports_compilers_missing_expected_errors() {
+        let result = compare_rejections(
+            COMPILERS,
+            Some(RUSTC),
+            Some("case.rs:2:18: error: cannot assign\n"),
+            &expectation(&["E0308"], &[]),
+            &[],
+        );
+        assert_eq!(
+            result.differences,
+            [Diff::ErrorMismatch {
+                compiler: "gccrs".to_string(),
+                missing: vec!["error code E0308".to_string()],
+                diagnostics: "case.rs:2:18: error: cannot assign\n".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_a_compiler_accepting_the_program() {
+        let expectation = expectation(&["E0308"], &[]);
+        let result = compare_rejections(COMPILERS, Some(RUSTC), None, &expectation, &[]);
+        assert_eq!(
+            result.differences,
+            [Diff::Rejection {
+                left: Some(RUSTC.to_string()),
+                right: None,
+            }]
+        );
+
+        let result = compare_rejections(COMPILERS, None, None, &expectation, &[]);
+        assert_eq!(
+            result.differences,
+            [Diff::Rejection {
+                left: None,
+                right: None,
+            }]
+        );
+    }
+}
@@ -1,33 +1,50 @@
-use super::compare::Comparison;
-use super::executor::ExecutionContext;
+use super::compare::{CompareOptions, Comparison};
+use super::executor::{ExecutionContext, ExecutionResult, ExecutionSettings};
 use super::AnalysisError;
 use std::path::Path;
-use std::time::Duration;
 
 pub struct AnalysisContext {
     pub testname: String,
-    pub gccrs: ExecutionContext,
-    pub rustc: ExecutionContext,
+    /// Names of the compilers that built the two binaries, shown as `-` and
+    /// `+` in diffs.
+    pub compilers: [String; 2],
+    pub left: ExecutionContext,
+    pub right: ExecutionContext,
+    pub options: CompareOptions,
 }
 
 impl AnalysisContext {
     pub fn new(
         testname: String,
-        gccrs_binary: &Path,
-        rustc_binary: &Path,
-        timeout: Duration,
+        compilers: [&str; 2],
+        [left_binary, right_binary]: [&Path; 2],
+        settings: &ExecutionSettings,
+        options: &CompareOptions,
     ) -> Self {
         Self {
             testname,
-            gccrs: ExecutionContext::new(gccrs_binary, timeout),
-            rustc: ExecutionContext::new(rustc_binary, timeout),
+            compilers: compilers.map(str::to_string),
+            left: ExecutionContext::new(left_binary, settings.clone()),
+            right: ExecutionContext::new(right_binary, settings.clone()),
+            options: options.clone(),
         }
     }
 
+    /// Runs only the right binary, built by the reference compiler that
+    /// golden files are blessed from.
+    pub fn run_reference(&self) -> Result<ExecutionResult, AnalysisError> {
+        Ok(self.right.run_binary()?)
+    }
+
     pub fn analyze(&self) -> Result<(), AnalysisError> {
-        let gccrs_exec_result = self.gccrs.run_binary()?;
-        let rustc_exec_result = self.rustc.run_binary()?;
-        let compare = Comparison::new(gccrs_exec_result, rustc_exec_result);
+        let left_exec_result = self.left.run_binary()?;
+        let right_exec_result = self.right.run_binary()?;
+        let compare = Comparison::new(
+            self.compilers.clone(),
+            left_exec_result,
+            right_exec_result,
+            self.options.clone(),
+        );
         let result = compare.compare();
         if result.is_identical() {
             Ok(())
@@ -0,0 +1,348 @@
+use crate::compiler::CompilerKind;
+use regex::Regex;
+use serde_derive::Deserialize;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// A warning or error printed by a compiler.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub level: String,
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+/// Extracts the warnings and errors from compiler output, written either in
+/// rustc's format (`warning: message` followed by ` --> file:line:col`) or in
+/// GCC's (`file:line:col: warning: message`).
+pub fn parse_diagnostics(output: &str) -> Vec<Diagnostic> {
+    static GCC: OnceLock<Regex> = OnceLock::new();
+    static RUSTC: OnceLock<Regex> = OnceLock::new();
+    static LOCATION: OnceLock<Regex> = OnceLock::new();
+    let gcc = GCC.get_or_init(|| {
+        Regex::new(r"^[^\s:][^:]*:(\d+):(?:\d+:)? (warning|error): (.*)$").unwrap()
+    });
+    let rustc = RUSTC.get_or_init(|| Regex::new(r"^(warning|error)(?:\[\w+\])?: (.*)$").unwrap());
+    let location = LOCATION.get_or_init(|| Regex::new(r"^\s*--> .*?:(\d+):\d+$").unwrap());
+
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    // Whether the last rustc-style header still waits for its location.
+    let mut pending = false;
+    for line in output.lines() {
+        if let Some(captures) = gcc.captures(line) {
+            diagnostics.push(Diagnostic {
+                level: captures[2].to_string(),
+                message: captures[3].to_string(),
+                line: captures[1].parse().ok(),
+            });
+            pending = false;
+        } else if let Some(captures) = rustc.captures(line) {
+            diagnostics.push(Diagnostic {
+                level: captures[1].to_string(),
+                message: captures[2].to_string(),
+                line: None,
+            });
+            pending = true;
+        } else if let Some(captures) = location.captures(line).filter(|_| pending) {
+            if let Some(diagnostic) = diagnostics.last_mut() {
+                diagnostic.line = captures[1].parse().ok();
+            }
+            pending = false;
+        }
+    }
+    diagnostics
+}
+
+/// Pairs a rustc lint with the gccrs warning that reports the same problem.
+/// Each side matches the message of a warning, without its location, from
+/// compilers of that dialect.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "LintMappingSpec")]
+pub struct LintMapping {
+    pub name: String,
+    pub rustc: Regex,
+    pub gccrs: Regex,
+}
+
+#[derive(Deserialize)]
+struct LintMappingSpec {
+    name: String,
+    rustc: String,
+    gccrs: String,
+}
+
+impl TryFrom<LintMappingSpec> for LintMapping {
+    type Error = String;
+
+    fn try_from(spec: LintMappingSpec) -> Result<Self, Self::Error> {
+        LintMapping::new(&spec.name, &spec.rustc, &spec.gccrs)
+    }
+}
+
+impl LintMapping {
+    pub fn new(name: &str, rustc: &str, gccrs: &str) -> Result<Self, String> {
+        let regex = |pattern: &str| {
+            Regex::new(pattern).map_err(|e| format!("invalid pattern for lint '{}': {}", name, e))
+        };
+        Ok(Self {
+            name: name.to_string(),
+            rustc: regex(rustc)?,
+            gccrs: regex(gccrs)?,
+        })
+    }
+
+    /// The lints compared when the config file does not list its own.
+    pub fn defaults() -> Vec<Self> {
+        [
+            ("unused_variables", "^unused variable", "^unused name"),
+            (
+                "dead_code",
+                "is never (used|constructed|read)",
+                "is never (used|constructed|read)",
+            ),
+        ]
+        .into_iter()
+        .map(|(name, rustc, gccrs)| Self::new(name, rustc, gccrs).unwrap())
+        .collect()
+    }
+
+    /// Source lines of the warnings in `diagnostics`, emitted by a compiler
+    /// of `dialect`, that report this lint.
+    fn lines(&self, dialect: CompilerKind, diagnostics: &[Diagnostic]) -> Vec<Option<usize>> {
+        let pattern = match dialect {
+            CompilerKind::Rustc => &self.rustc,
+            CompilerKind::Gccrs => &self.gccrs,
+        };
+        let mut lines: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.level == "warning" && pattern.is_match(&d.message))
+            .map(|d| d.line)
+            .collect();
+        lines.sort_unstable();
+        lines
+    }
+}
+
+/// A mapped warning that only one of the compilers reported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticMismatch {
+    pub lint: String,
+    pub line: Option<usize>,
+    /// The compiler that reported the warning.
+    pub compiler: String,
+    /// The compiler that did not.
+    pub other: String,
+}
+
+impl fmt::Display for DiagnosticMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.lint)?;
+        if let Some(line) = self.line {
+            write!(f, " at line {}", line)?;
+        }
+        write!(
+            f,
+            " reported by {} but not by {}",
+            self.compiler, self.other
+        )
+    }
+}
+
+/// Compares the warnings two compilers printed, given with their name and
+/// dialect, for every lint in `lints`. Unmapped diagnostics are not compared.
+///
+/// With `by_line` warnings are paired by source line, which needs both
+/// compilers to have built files with the same layout. Otherwise only the
+/// number of warnings per lint is compared.
+pub fn compare_diagnostics(
+    [(left_name, left_dialect), (right_name, right_dialect)]: [(&str, CompilerKind); 2],
+    [left, right]: [&str; 2],
+    lints: &[LintMapping],
+    by_line: bool,
+) -> Vec<DiagnosticMismatch> {
+    let without_lines = |mut diagnostics: Vec<Diagnostic>| {
+        if !by_line {
+            diagnostics.iter_mut().for_each(|d| d.line = None);
+        }
+        diagnostics
+    };
+    let left = without_lines(parse_diagnostics(left));
+    let right = without_lines(parse_diagnostics(right));
+    let mismatch = |line, compiler: &str, other: &str| DiagnosticMismatch {
+        lint: String::new(),
+        line,
+        compiler: compiler.to_string(),
+        other: other.to_string(),
+    };
+
+    let mut mismatches = Vec::new();
+    for lint in lints {
+        let named = |mismatch: DiagnosticMismatch| DiagnosticMismatch {
+            lint: lint.name.clone(),
+            ..mismatch
+        };
+        let mut right_lines = lint.lines(right_dialect, &right);
+        for line in lint.lines(left_dialect, &left) {
+            match right_lines.iter().position(|other| *other == line) {
+                Some(index) => {
+                    right_lines.remove(index);
+                }
+                None => mismatches.push(named(mismatch(line, left_name, right_name))),
+            }
+        }
+        mismatches.extend(
+            right_lines
+                .into_iter()
+                .map(|line| named(mismatch(line, right_name, left_name))),
+        );
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUSTC: &str = "\
+warning: unused variable: `x`
+ --> src/main.rs:3:9
+  |
+3 |     let x = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_x`
+
+warning[E0170]: function `helper` is never used
+  --> src/main.rs:7:4
+error: aborting due to 1 previous error
+";
+
+    const GCCRS: &str = "\
+gccrs.rs:3:9: warning: unused name 'x'
+    3 |     let x = 1;
+      |         ^
+gccrs.rs:7:1: warning: function is never used: 'helper'
+gccrs.rs:12: error: mismatched types
+";
+
+    fn diagnostic(level: &str, message: &str, line: Option<usize>) -> Diagnostic {
+        Diagnostic {
+            level: level.to_string(),
+            message: message.to_string(),
+            line,
+        }
+    }
+
+    fn compare(rustc: &str, gccrs: &str, by_line: bool) -> Vec<DiagnosticMismatch> {
+        compare_diagnostics(
+            [
+                ("gccrs", CompilerKind::Gccrs),
+                ("rustc", CompilerKind::Rustc),
+            ],
+            [gccrs, rustc],
+            &LintMapping::defaults(),
+            by_line,
+        )
+    }
+
+    fn mismatch(lint: &str, line: Option<usize>, compiler: &str) -> DiagnosticMismatch {
+        DiagnosticMismatch {
+            lint: lint.to_string(),
+            line,
+            compiler: compiler.to_string(),
+            other: if compiler == "gccrs" {
+                "rustc"
+            } else {
+                "gccrs"
+            }
+            .to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_rustc_format() {
+        assert_eq!(
+            parse_diagnostics(RUSTC),
+            [
+                diagnostic("warning", "unused variable: `x`", Some(3)),
+                diagnostic("warning", "function `helper` is never used", Some(7)),
+                diagnostic("error", "aborting due to 1 previous error", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_gcc_format() {
+        assert_eq!(
+            parse_diagnostics(GCCRS),
+            [
+                diagnostic("warning", "unused name 'x'", Some(3)),
+                diagnostic("warning", "function is never used: 'helper'", Some(7)),
+                diagnostic("error", "mismatched types", Some(12)),
+            ]
+        );
+    }
+
+    #[test]
+    fn location_only_belongs_to_the_header_before_it() {
+        assert_eq!(
+            parse_diagnostics("a.rs:2:1: warning: first\n --> src/main.rs:9:1\n"),
+            [diagnostic("warning", "first", Some(2))]
+        );
+    }
+
+    #[test]
+    fn maps_lints_by_dialect() {
+        let lint = &LintMapping::defaults()[0];
+        let rustc = parse_diagnostics(RUSTC);
+        assert_eq!(lint.lines(CompilerKind::Rustc, &rustc), [Some(3)]);
+        assert!(lint.lines(CompilerKind::Gccrs, &rustc).is_empty());
+        assert_eq!(
+            lint.lines(CompilerKind::Gccrs, &parse_diagnostics(GCCRS)),
+            [Some(3)]
+        );
+    }
+
+    #[test]
+    fn matching_warnings_agree() {
+        assert!(compare(RUSTC, GCCRS, true).is_empty());
+        assert!(compare(RUSTC, GCCRS, false).is_empty());
+    }
+
+    #[test]
+    fn reports_warnings_only_one_compiler_emits() {
+        let gccrs = "gccrs.rs:3:9: warning: unused name 'x'\n";
+        assert_eq!(
+            compare(RUSTC, gccrs, true),
+            [mismatch("dead_code", Some(7), "rustc")]
+        );
+        assert_eq!(
+            compare("", gccrs, true),
+            [mismatch("unused_variables", Some(3), "gccrs")]
+        );
+    }
+
+    #[test]
+    fn different_layouts_compare_counts_only() {
+        let gccrs = "gccrs.rs:5:9: warning: unused name 'x'\ngccrs.rs:9:1: warning: function is never used: 'helper'\n";
+        assert_eq!(
+            compare(RUSTC, gccrs, true),
+            [
+                mismatch("unused_variables", Some(5), "gccrs"),
+                mismatch("unused_variables", Some(3), "rustc"),
+                mismatch("dead_code", Some(9), "gccrs"),
+                mismatch("dead_code", Some(7), "rustc"),
+            ]
+        );
+        assert!(compare(RUSTC, gccrs, false).is_empty());
+
+        let twice = "g.rs:1:1: warning: unused name 'a'\ng.rs:2:1: warning: unused name 'b'\n";
+        assert_eq!(
+            compare("warning: unused variable: `a`\n", twice, false),
+            [mismatch("unused_variables", None, "gccrs")]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_lint_patterns() {
+        assert!(LintMapping::new("broken", "(", "x").is_err());
+    }
+}
@@ -1,18 +1,57 @@
+use super::limits::{Resource, ResourceLimits};
+use super::termination::{Termination, TerminationKind};
+use regex::Regex;
+use std::io::{self, Read, Write};
+use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::OnceLock;
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 use thiserror::Error;
 use wait_timeout::ChildExt;
 
 pub struct ExecutionContext {
     pub binary: PathBuf,
+    pub settings: ExecutionSettings,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutionSettings {
     pub timeout: Duration,
+    /// Bytes captured per output stream, anything beyond is discarded.
+    pub output_limit: usize,
+    pub inputs: ExecutionInputs,
+    pub limits: ResourceLimits,
+}
+
+/// Everything a test binary receives from the outside. The environment is
+/// cleared, so `env` is the complete set of variables the binary sees.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExecutionInputs {
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub stdin: Vec<u8>,
 }
 
 #[derive(Debug)]
 pub struct ExecutionResult {
-    pub output: Option<Output>,
+    pub output: Option<ExecutionOutput>,
     pub timed_out: bool,
+    pub termination: Termination,
+}
+
+#[derive(Debug)]
+pub struct ExecutionOutput {
+    pub status: ExitStatus,
+    pub stdout: CapturedOutput,
+    pub stderr: CapturedOutput,
+}
+
+#[derive(Debug, Default)]
+pub struct CapturedOutput {
+    pub bytes: Vec<u8>,
+    pub truncated: bool,
 }
 
 #[derive(Debug, Error, PartialEq)]
@@ -22,7 +61,7 @@ pub enum ExecutionError {
 }
 
 impl ExecutionContext {
-    pub fn new(binary: &Path, timeout: Duration) -> Self {
+    pub fn new(binary: &Path, settings: ExecutionSettings) -> Self {
         assert!(
             binary.exists() && binary.is_file(),
             "Invalid binary path: {:?}",
@@ -30,45 +69,226 @@ impl ExecutionContext {
         );
         Self {
             binary: binary.to_path_buf(),
-            timeout,
+            settings,
         }
     }
 
     pub fn run_binary(&self) -> Result<ExecutionResult, ExecutionError> {
-        let binary = self.binary.clone();
-        let timeout = self.timeout;
-
-        let mut child = Command::new(binary)
+        let inputs = &self.settings.inputs;
+        let mut command = Command::new(&self.binary);
+        command
+            .args(&inputs.args)
+            .env_clear()
+            .envs(inputs.env.iter().map(|(name, value)| (name, value)))
+            .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            // Its own process group, so that a timeout also ends whatever
+            // the binary forked.
+            .process_group(0);
+        let limits = self.settings.limits;
+        if !limits.is_empty() {
+            // SAFETY: `apply` only calls `setrlimit`, which is async-signal-safe.
+            unsafe {
+                command.pre_exec(move || limits.apply());
+            }
+        }
+        let mut child = command
             .spawn()
             .map_err(|e| ExecutionError::Failed(e.to_string()))?;
 
+        // Feed stdin from its own thread so a binary that does not read all
+        // of it cannot block us. Dropping the handle closes the pipe.
+        if let Some(mut stdin) = child.stdin.take() {
+            let input = inputs.stdin.clone();
+            thread::spawn(move || stdin.write_all(&input));
+        }
+
+        // Both pipes are drained while we wait, otherwise a binary that
+        // fills a pipe buffer blocks until it is killed by the timeout.
+        let limit = self.settings.output_limit;
+        let stdout = child.stdout.take().map(|pipe| spawn_capture(pipe, limit));
+        let stderr = child.stderr.take().map(|pipe| spawn_capture(pipe, limit));
+
         match child
-            .wait_timeout(timeout)
+            .wait_timeout(self.settings.timeout)
             .map_err(|e| ExecutionError::Failed(e.to_string()))?
         {
-            Some(_) => {
-                let output = child
-                    .wait_with_output()
+            Some(status) => {
+                // Processes the binary left running would hold the pipes
+                // open and keep the readers from finishing.
+                kill_process_group(&mut child)
                     .map_err(|e| ExecutionError::Failed(e.to_string()))?;
-
+                let output = ExecutionOutput {
+                    status,
+                    stdout: join_capture(stdout)?,
+                    stderr: join_capture(stderr)?,
+                };
+                let mut termination = Termination::from_status(status);
+                if let Some(resource) =
+                    exceeded_limit(&self.settings.limits, &termination, &output.stderr.bytes)
+                {
+                    termination.kind = TerminationKind::LimitExceeded(resource);
+                }
                 Ok(ExecutionResult {
                     output: Some(output),
                     timed_out: false,
+                    termination,
                 })
             }
             None => {
-                child
-                    .kill()
+                kill_process_group(&mut child)
                     .map_err(|e| ExecutionError::Failed(e.to_string()))?;
-                child.wait().ok();
 
+                // The readers are left to finish on their own: a process
+                // that left the group may still hold the pipes open.
                 Ok(ExecutionResult {
                     output: None,
                     timed_out: true,
+                    termination: Termination::timed_out(),
                 })
             }
         }
     }
 }
+
+/// Tells which limit ended the run, for the limits the kernel reports with a
+/// signal and for allocation failures under an address space limit.
+///
+/// Failed allocations are only recognized the way the Rust runtime reports
+/// them: a `memory allocation of N bytes failed` line followed by an abort.
+/// A binary whose runtime handles them differently shows up as a crash or
+/// an abort instead. Running out of processes or open files only shows in
+/// how the binary handles the failed call.
+fn exceeded_limit(
+    limits: &ResourceLimits,
+    termination: &Termination,
+    stderr: &[u8],
+) -> Option<Resource> {
+    static OUT_OF_MEMORY: OnceLock<Regex> = OnceLock::new();
+    match termination.signal {
+        Some(libc::SIGXCPU) if limits.cpu_time.is_some() => Some(Resource::CpuTime),
+        Some(libc::SIGXFSZ) if limits.file_size.is_some() => Some(Resource::FileSize),
+        Some(libc::SIGABRT)
+            if limits.address_space.is_some()
+                && OUT_OF_MEMORY
+                    .get_or_init(|| {
+                        Regex::new(r"(?m)^memory allocation of \d+ bytes failed$").unwrap()
+                    })
+                    .is_match(&String::from_utf8_lossy(stderr)) =>
+        {
+            Some(Resource::AddressSpace)
+        }
+        _ => None,
+    }
+}
+
+/// Kills every process in the process group of `child`, which has to lead
+/// its own group (see [`CommandExt::process_group`]), and reaps `child`.
+pub fn kill_process_group(child: &mut Child) -> io::Result<()> {
+    // SAFETY: `killpg` only sends a signal and takes no pointers.
+    if unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) } != 0 {
+        let error = io::Error::last_os_error();
+        // The group is already gone once all of its members exited.
+        if error.raw_os_error() != Some(libc::ESRCH) {
+            return Err(error);
+        }
+    }
+    child.wait().map(drop)
+}
+
+/// Reads `pipe` to the end on a background thread, keeping at most `limit`
+/// bytes.
+pub fn spawn_capture<R>(mut pipe: R, limit: usize) -> JoinHandle<io::Result<CapturedOutput>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut captured = CapturedOutput::default();
+        let mut buffer = [0; 8192];
+        loop {
+            let read = match pipe.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let room = limit.saturating_sub(captured.bytes.len());
+            if read > room {
+                captured.truncated = true;
+            }
+            captured.bytes.extend_from_slice(&buffer[..read.min(room)]);
+        }
+        Ok(captured)
+    })
+}
+
+fn join_capture(
+    handle: Option<JoinHandle<io::Result<CapturedOutput>>>,
+) -> Result<CapturedOutput, ExecutionError> {
+    match handle {
+        Some(handle) => handle
+            .join()
+            .map_err(|_| ExecutionError::Failed("output reader panicked".to_string()))?
+            .map_err(|e| ExecutionError::Failed(e.to_string())),
+        None => Ok(CapturedOutput::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    const OUT_OF_MEMORY: &[u8] = b"memory allocation of 1048576 bytes failed\n";
+
+    fn killed(signal: i32) -> Termination {
+        Termination::from_status(ExitStatus::from_raw(signal))
+    }
+
+    #[test]
+    fn signals_of_set_limits() {
+        let limits = ResourceLimits {
+            cpu_time: Some(1),
+            file_size: Some(1024),
+            ..ResourceLimits::default()
+        };
+        assert_eq!(
+            exceeded_limit(&limits, &killed(libc::SIGXCPU), b""),
+            Some(Resource::CpuTime)
+        );
+        assert_eq!(
+            exceeded_limit(&limits, &killed(libc::SIGXFSZ), b""),
+            Some(Resource::FileSize)
+        );
+        assert_eq!(exceeded_limit(&limits, &killed(libc::SIGSEGV), b""), None);
+        assert_eq!(
+            exceeded_limit(&ResourceLimits::default(), &killed(libc::SIGXCPU), b""),
+            None
+        );
+    }
+
+    #[test]
+    fn failed_allocations_under_an_address_space_limit() {
+        let limits = ResourceLimits {
+            address_space: Some(1 << 20),
+            ..ResourceLimits::default()
+        };
+        let aborted = killed(libc::SIGABRT);
+        assert_eq!(
+            exceeded_limit(&limits, &aborted, OUT_OF_MEMORY),
+            Some(Resource::AddressSpace)
+        );
+        assert_eq!(
+            exceeded_limit(&ResourceLimits::default(), &aborted, OUT_OF_MEMORY),
+            None
+        );
+        // Printing the message is not enough, the run has to abort.
+        let exited = Termination::from_status(ExitStatus::from_raw(0));
+        assert_eq!(exceeded_limit(&limits, &exited, OUT_OF_MEMORY), None);
+        assert_eq!(
+            exceeded_limit(&limits, &aborted, b"error: memory allocation of x failed\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use regex::Regex;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+const DEFAULT_ABSOLUTE: f64 = 1e-12;
+const DEFAULT_RELATIVE: f64 = 1e-9;
+
+/// How stdout of the two binaries is compared.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum StdoutMode {
+    #[default]
+    Exact,
+    /// Numbers are compared by value within the tolerance, the text around
+    /// them exactly.
+    Float(FloatTolerance),
+}
+
+/// Two numbers match when they are within `absolute` of each other, or
+/// within `relative` times the larger magnitude.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatTolerance {
+    pub absolute: f64,
+    pub relative: f64,
+}
+
+impl Default for FloatTolerance {
+    fn default() -> Self {
+        Self {
+            absolute: DEFAULT_ABSOLUTE,
+            relative: DEFAULT_RELATIVE,
+        }
+    }
+}
+
+/// Parses the value of a `//@ stdout-mode:` directive: `exact`, or `float`
+/// optionally followed by `abs=<tolerance>` and `rel=<tolerance>`.
+impl FromStr for StdoutMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut words = value.split_whitespace();
+        match words.next() {
+            Some("exact") if words.next().is_none() => Ok(Self::Exact),
+            Some("float") => {
+                let mut tolerance = FloatTolerance::default();
+                for setting in words {
+                    let (name, number) = setting.split_once('=').ok_or_else(|| {
+                        format!("expected `abs=..` or `rel=..`, found '{}'", setting)
+                    })?;
+                    let number: f64 = number
+                        .parse()
+                        .ok()
+                        .filter(|number: &f64| *number >= 0.0)
+                        .ok_or_else(|| format!("invalid tolerance '{}'", number))?;
+                    match name {
+                        "abs" => tolerance.absolute = number,
+                        "rel" => tolerance.relative = number,
+                        _ => return Err(format!("unknown tolerance '{}'", name)),
+                    }
+                }
+                Ok(Self::Float(tolerance))
+            }
+            _ => Err(format!("unknown stdout mode '{}'", value)),
+        }
+    }
+}
+
+/// One position at which the token streams of the two outputs disagree.
+/// A side that ran out of tokens is `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenMismatch {
+    pub line: usize,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    /// Digits without a fraction or exponent, such as a counter or checksum,
+    /// which has to match exactly.
+    Integer(&'a str, i128),
+    Float(&'a str, f64),
+    Text(&'a str),
+}
+
+struct Located<'a> {
+    line: usize,
+    token: Token<'a>,
+}
+
+/// Splits `text` into numbers and the text between them. Infinities and
+/// NaNs are recognized in any case, as Rust (`inf`, `NaN`) and C's `printf`
+/// (`inf`, `nan`, `-nan`) write them.
+fn tokenize(text: &str) -> Vec<Located<'_>> {
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    let number = NUMBER.get_or_init(|| {
+        Regex::new(r"[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?|[-+]?\b(?i:inf(?:inity)?|nan)\b")
+            .unwrap()
+    });
+
+    let mut tokens = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let mut last = 0;
+        for found in number.find_iter(line) {
+            if found.start() > last {
+                tokens.push(Located {
+                    line: line_number,
+                    token: Token::Text(&line[last..found.start()]),
+                });
+            }
+            let text = found.as_str();
+            let token = if text
+                .bytes()
+                .all(|b| b.is_ascii_digit() || b == b'-' || b == b'+')
+            {
+                // Integers too large for `i128` are compared as text.
+                text.parse()
+                    .map_or(Token::Text(text), |value| Token::Integer(text, value))
+            } else {
+                text.parse()
+                    .map_or(Token::Text(text), |value| Token::Float(text, value))
+            };
+            tokens.push(Located {
+                line: line_number,
+                token,
+            });
+            last = found.end();
+        }
+        if last < line.len() {
+            tokens.push(Located {
+                line: line_number,
+                token: Token::Text(&line[last..]),
+            });
+        }
+    }
+    tokens
+}
+
+impl FloatTolerance {
+    /// NaNs match each other whatever their sign, as `printf` writes some
+    /// of them as `-nan`. Infinities only match themselves.
+    fn matches(&self, a: f64, b: f64) -> bool {
+        if a.is_nan() || b.is_nan() {
+            return a.is_nan() && b.is_nan();
+        }
+        if a == b {
+            return true;
+        }
+        if a.is_infinite() || b.is_infinite() {
+            return false;
+        }
+        let difference = (a - b).abs();
+        difference <= self.absolute || difference <= self.relative * a.abs().max(b.abs())
+    }
+
+    /// Compares two outputs token by token. Lines are not significant on
+    /// their own, only the tokens on them.
+    pub fn compare(&self, left: &str, right: &str) -> Vec<TokenMismatch> {
+        let left_tokens = tokenize(left);
+        let right_tokens = tokenize(right);
+
+        let mut mismatches = Vec::new();
+        for index in 0..left_tokens.len().max(right_tokens.len()) {
+            let (left, right) = (left_tokens.get(index), right_tokens.get(index));
+            let equal = match (left.map(|t| &t.token), right.map(|t| &t.token)) {
+                (Some(Token::Integer(_, a)), Some(Token::Integer(_, b))) => a == b,
+                (Some(a), Some(b)) => match (a.value(), b.value()) {
+                    (Some(a), Some(b)) => self.matches(a, b),
+                    _ => a.as_str() == b.as_str(),
+                },
+                _ => false,
+            };
+            if !equal {
+                mismatches.push(TokenMismatch {
+                    line: left.or(right).map_or(0, |t| t.line),
+                    left: left.map(|t| t.token.as_str().to_string()),
+                    right: right.map(|t| t.token.as_str().to_string()),
+                });
+            }
+        }
+        mismatches
+    }
+}
+
+impl Token<'_> {
+    fn as_str(&self) -> &str {
+        match self {
+            Token::Integer(text, _) | Token::Float(text, _) | Token::Text(text) => text,
+        }
+    }
+
+    /// The numeric value of the token, to compare an integer with a float
+    /// by value.
+    fn value(&self) -> Option<f64> {
+        match self {
+            Token::Integer(_, value) => Some(*value as f64),
+            Token::Float(_, value) => Some(*value),
+            Token::Text(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<Token<'_>> {
+        tokenize(text).into_iter().map(|t| t.token).collect()
+    }
+
+    #[test]
+    fn tokenize_splits_numbers_from_text() {
+        assert_eq!(
+            tokens("x = -1.5e3, n=42"),
+            [
+                Token::Text("x = "),
+                Token::Float("-1.5e3", -1500.0),
+                Token::Text(", n="),
+                Token::Integer("42", 42),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_recognizes_infinities_and_nans() {
+        let found = tokens("inf -inf Infinity nan -nan NaN");
+        let values: Vec<_> = found
+            .iter()
+            .filter_map(|token| match token {
+                Token::Float(_, value) => Some(*value),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(values.len(), 6);
+        assert_eq!(
+            values[..3],
+            [f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY]
+        );
+        assert!(values[3..].iter().all(|value| value.is_nan()));
+    }
+
+    #[test]
+    fn tokenize_does_not_split_words() {
+        assert_eq!(tokens("information"), [Token::Text("information")]);
+    }
+
+    #[test]
+    fn tokenize_keeps_huge_integers_as_text() {
+        let digits = "1".repeat(50);
+        assert_eq!(tokens(&digits), [Token::Text(&digits)]);
+    }
+
+    #[test]
+    fn floats_match_within_tolerance() {
+        let tolerance = FloatTolerance::default();
+        assert!(tolerance
+            .compare("pi 3.141592653589793", "pi 3.1415926535897936")
+            .is_empty());
+        assert_eq!(tolerance.compare("1.5", "1.6").len(), 1);
+    }
+
+    #[test]
+    fn integers_match_exactly() {
+        let tolerance = FloatTolerance {
+            absolute: 1.0,
+            relative: 1.0,
+        };
+        assert_eq!(
+            tolerance.compare("checksum 9007199254740993", "checksum 9007199254740992"),
+            [TokenMismatch {
+                line: 1,
+                left: Some("9007199254740993".to_string()),
+                right: Some("9007199254740992".to_string()),
+            }]
+        );
+        assert!(tolerance.compare("1", "1.5").is_empty());
+    }
+
+    #[test]
+    fn nans_match_whatever_their_sign() {
+        let tolerance = FloatTolerance::default();
+        assert!(tolerance.compare("nan", "-nan").is_empty());
+        assert!(tolerance.compare("NaN", "nan").is_empty());
+        assert_eq!(tolerance.compare("nan", "0").len(), 1);
+        assert_eq!(tolerance.compare("inf", "-inf").len(), 1);
+    }
+
+    #[test]
+    fn missing_tokens_are_mismatches() {
+        assert_eq!(
+            FloatTolerance::default().compare("a 1\nb 2", "a 1"),
+            [
+                TokenMismatch {
+                    line: 2,
+                    left: Some("b ".to_string()),
+                    right: None,
+                },
+                TokenMismatch {
+                    line: 2,
+                    left: Some("2".to_string()),
+                    right: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_stdout_mode() {
+        assert_eq!("exact".parse(), Ok(StdoutMode::Exact));
+        assert_eq!(
+            "float abs=0.5".parse(),
+            Ok(StdoutMode::Float(FloatTolerance {
+                absolute: 0.5,
+                ..FloatTolerance::default()
+            }))
+        );
+        assert!("float abs=-1".parse::<StdoutMode>().is_err());
+        assert!("float tol=1".parse::<StdoutMode>().is_err());
+        assert!("exact float".parse::<StdoutMode>().is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use serde_derive::Deserialize;
+use std::io;
+
+/// A limited resource whose exhaustion can be told from how the binary
+/// ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    AddressSpace,
+    CpuTime,
+    FileSize,
+}
+
+impl Resource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Resource::AddressSpace => "address-space",
+            Resource::CpuTime => "cpu-time",
+            Resource::FileSize => "file-size",
+        }
+    }
+}
+
+/// Limits applied with `setrlimit` to a test binary before it is executed.
+/// Unset limits are inherited from this process.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// Bytes of virtual memory.
+    pub address_space: Option<u64>,
+    /// Seconds of CPU time.
+    pub cpu_time: Option<u64>,
+    pub open_files: Option<u64>,
+    /// Processes of the user running the tests, not only of the binary.
+    pub processes: Option<u64>,
+    /// Bytes of any file the binary writes.
+    pub file_size: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Limits of `self`, falling back to `defaults` for those not set.
+    pub fn or(self, defaults: ResourceLimits) -> Self {
+        Self {
+            address_space: self.address_space.or(defaults.address_space),
+            cpu_time: self.cpu_time.or(defaults.cpu_time),
+            open_files: self.open_files.or(defaults.open_files),
+            processes: self.processes.or(defaults.processes),
+            file_size: self.file_size.or(defaults.file_size),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sets one limit from a `//@ limit: <resource>=<value>` directive. Sizes
+    /// accept a `K`, `M` or `G` suffix.
+    pub fn set(&mut self, directive: &str) -> Result<(), String> {
+        let (name, value) = directive
+            .split_once('=')
+            .ok_or_else(|| format!("expected `RESOURCE=VALUE`, found '{}'", directive))?;
+        let value = value.trim();
+        let (digits, multiplier) = match value.char_indices().last() {
+            Some((index, 'K')) => (&value[..index], 1 << 10),
+            Some((index, 'M')) => (&value[..index], 1 << 20),
+            Some((index, 'G')) => (&value[..index], 1 << 30),
+            _ => (value, 1),
+        };
+        let amount = digits
+            .parse::<u64>()
+            .ok()
+            .and_then(|amount| amount.checked_mul(multiplier))
+            .ok_or_else(|| format!("invalid limit '{}'", value))?;
+
+        let limit = match name.trim() {
+            "address-space" => &mut self.address_space,
+            "cpu-time" => &mut self.cpu_time,
+            "open-files" => &mut self.open_files,
+            "processes" => &mut self.processes,
+            "file-size" => &mut self.file_size,
+            other => return Err(format!("unknown resource '{}'", other)),
+        };
+        *limit = Some(amount);
+        Ok(())
+    }
+
+    /// Applies the limits to the calling process. Runs in the forked child
+    /// before `exec`, so it must not allocate.
+    pub fn apply(&self) -> io::Result<()> {
+        // The CPU hard limit is one second above the soft one, so that the
+        // binary gets `SIGXCPU` rather than `SIGKILL` when it runs out.
+        let limits = [
+            (libc::RLIMIT_AS, self.address_space, 0),
+            (libc::RLIMIT_CPU, self.cpu_time, 1),
+            (libc::RLIMIT_NOFILE, self.open_files, 0),
+            (libc::RLIMIT_NPROC, self.processes, 0),
+            (libc::RLIMIT_FSIZE, self.file_size, 0),
+        ];
+        for (resource, limit, slack) in limits {
+            let Some(limit) = limit else {
+                continue;
+            };
+            let rlimit = libc::rlimit {
+                rlim_cur: limit as libc::rlim_t,
+                rlim_max: limit.saturating_add(slack) as libc::rlim_t,
+            };
+            // SAFETY: `rlimit` is a valid, initialized struct for the call.
+            if unsafe { libc::setrlimit(resource, &rlimit) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(directives: &[&str]) -> Result<ResourceLimits, String> {
+        let mut limits = ResourceLimits::default();
+        for directive in directives {
+            limits.set(directive)?;
+        }
+        Ok(limits)
+    }
+
+    #[test]
+    fn sets_each_resource() {
+        assert_eq!(
+            parse(&[
+                "address-space=64M",
+                "cpu-time = 2",
+                "open-files=16",
+                "processes=8",
+                "file-size=4K",
+            ]),
+            Ok(ResourceLimits {
+                address_space: Some(64 << 20),
+                cpu_time: Some(2),
+                open_files: Some(16),
+                processes: Some(8),
+                file_size: Some(4 << 10),
+            })
+        );
+        assert_eq!(
+            parse(&["address-space=1G"]).unwrap().address_space,
+            Some(1 << 30)
+        );
+    }
+
+    #[test]
+    fn later_directives_override_earlier_ones() {
+        assert_eq!(
+            parse(&["cpu-time=1", "cpu-time=3"]).unwrap().cpu_time,
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_resources() {
+        assert_eq!(parse(&["cpu=1"]), Err("unknown resource 'cpu'".to_string()));
+        assert!(parse(&["stack=1M"]).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        assert!(parse(&["cpu-time"]).is_err());
+        assert!(parse(&["cpu-time="]).is_err());
+        assert!(parse(&["cpu-time=-1"]).is_err());
+        assert!(parse(&["file-size=1T"]).is_err());
+        assert!(parse(&["file-size=1.5M"]).is_err());
+        assert!(parse(&["address-space=99999999999999999999G"]).is_err());
+        assert!(parse(&["address-space=17179869184G"]).is_err());
+    }
+
+    #[test]
+    fn falls_back_to_defaults() {
+        let defaults = parse(&["cpu-time=10", "open-files=64"]).unwrap();
+        let limits = parse(&["cpu-time=1"]).unwrap().or(defaults);
+        assert_eq!(limits.cpu_time, Some(1));
+        assert_eq!(limits.open_files, Some(64));
+        assert!(ResourceLimits::default().is_empty());
+        assert!(!limits.is_empty());
+    }
+}
@@ -1,11 +1,29 @@
 mod compare;
+mod compile_fail;
 mod context;
+mod diagnostics;
 mod executor;
+mod float;
+mod limits;
+mod normalize;
 mod result;
+mod stderr;
+mod termination;
 
+pub use compare::{CompareOptions, Comparison, Expected};
+pub use compile_fail::{compare_rejections, CompileFailExpectation};
 pub use context::AnalysisContext;
-pub use executor::ExecutionError;
-pub use result::AnalysisResult;
+pub use diagnostics::{compare_diagnostics, DiagnosticMismatch, LintMapping};
+pub use executor::{
+    kill_process_group, spawn_capture, CapturedOutput, ExecutionContext, ExecutionError,
+    ExecutionInputs, ExecutionOutput, ExecutionResult, ExecutionSettings,
+};
+pub use float::{FloatTolerance, StdoutMode, TokenMismatch};
+pub use limits::{Resource, ResourceLimits};
+pub use normalize::Normalizer;
+pub use result::{format_mismatches, format_rejection, AnalysisResult, Diff};
+pub use stderr::StderrMode;
+pub use termination::{signal_name, Termination, TerminationKind};
 pub use thiserror::Error;
 
 #[derive(Debug, Error, PartialEq)]
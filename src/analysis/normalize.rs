@@ -0,0 +1,221 @@
+use regex::Regex;
+use serde_derive::Deserialize;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+const HEX_ADDRESS_MASK: &str = "0x<address>";
+
+/// A rewrite applied to the output of both binaries before it is compared,
+/// so that details which legitimately differ between runs, such as pointer
+/// values, do not count as differences.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "NormalizerSpec")]
+pub enum Normalizer {
+    /// Replaces every match of `pattern`, `$1` and `$name` refer to groups.
+    Regex {
+        pattern: Regex,
+        replacement: String,
+    },
+    /// Masks `0x`-prefixed hexadecimal numbers, as printed for pointers.
+    HexAddresses,
+    TrimTrailingWhitespace,
+    /// Turns `\r\n` into `\n`.
+    LineEndings,
+}
+
+/// How a normalizer is written in `Compiler.toml`, e.g.
+/// `{ kind = "regex", pattern = "pid \\d+", replacement = "pid <pid>" }`.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+enum NormalizerSpec {
+    Regex {
+        pattern: String,
+        replacement: String,
+    },
+    HexAddresses,
+    TrimTrailingWhitespace,
+    LineEndings,
+}
+
+impl TryFrom<NormalizerSpec> for Normalizer {
+    type Error = String;
+
+    fn try_from(spec: NormalizerSpec) -> Result<Self, Self::Error> {
+        Ok(match spec {
+            NormalizerSpec::Regex {
+                pattern,
+                replacement,
+            } => Self::regex(&pattern, replacement)?,
+            NormalizerSpec::HexAddresses => Self::HexAddresses,
+            NormalizerSpec::TrimTrailingWhitespace => Self::TrimTrailingWhitespace,
+            NormalizerSpec::LineEndings => Self::LineEndings,
+        })
+    }
+}
+
+/// Parses the value of a `//@ normalize:` directive: the kebab-case name of a
+/// normalizer, or `regex <pattern> => <replacement>`.
+impl FromStr for Normalizer {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "hex-addresses" => Ok(Self::HexAddresses),
+            "trim-trailing-whitespace" => Ok(Self::TrimTrailingWhitespace),
+            "line-endings" => Ok(Self::LineEndings),
+            _ => {
+                let (pattern, replacement) = value
+                    .strip_prefix("regex ")
+                    .and_then(|rule| rule.split_once(" => "))
+                    .ok_or_else(|| format!("unknown normalizer '{}'", value))?;
+                Self::regex(pattern.trim(), replacement.trim().to_string())
+            }
+        }
+    }
+}
+
+impl PartialEq for Normalizer {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Regex {
+                    pattern,
+                    replacement,
+                },
+                Self::Regex {
+                    pattern: other_pattern,
+                    replacement: other_replacement,
+                },
+            ) => pattern.as_str() == other_pattern.as_str() && replacement == other_replacement,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+impl Normalizer {
+    fn regex(pattern: &str, replacement: String) -> Result<Self, String> {
+        let pattern =
+            Regex::new(pattern).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?;
+        Ok(Self::Regex {
+            pattern,
+            replacement,
+        })
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Self::Regex {
+                pattern,
+                replacement,
+            } => pattern.replace_all(text, replacement.as_str()).into_owned(),
+            Self::HexAddresses => {
+                static HEX: OnceLock<Regex> = OnceLock::new();
+                HEX.get_or_init(|| Regex::new(r"\b0[xX][0-9a-fA-F]+\b").unwrap())
+                    .replace_all(text, HEX_ADDRESS_MASK)
+                    .into_owned()
+            }
+            Self::TrimTrailingWhitespace => text
+                .split_inclusive('\n')
+                .map(|line| match line.strip_suffix('\n') {
+                    Some(content) => format!("{}\n", content.trim_end()),
+                    None => line.trim_end().to_string(),
+                })
+                .collect(),
+            Self::LineEndings => text.replace("\r\n", "\n"),
+        }
+    }
+}
+
+/// Applies `normalizers` to `text` in order.
+pub fn normalize(text: &str, normalizers: &[Normalizer]) -> String {
+    normalizers
+        .iter()
+        .fold(text.to_string(), |text, normalizer| normalizer.apply(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Config {
+        normalize: Vec<Normalizer>,
+    }
+
+    #[test]
+    fn masks_hex_addresses() {
+        assert_eq!(
+            Normalizer::HexAddresses.apply("p = 0x7ffd1a2b, q = 0XABC, id0x1"),
+            "p = 0x<address>, q = 0x<address>, id0x1"
+        );
+    }
+
+    #[test]
+    fn trims_trailing_whitespace_on_every_line() {
+        assert_eq!(
+            Normalizer::TrimTrailingWhitespace.apply("a  \n b\t\r\n\nc "),
+            "a\n b\n\nc"
+        );
+    }
+
+    #[test]
+    fn converts_line_endings() {
+        assert_eq!(Normalizer::LineEndings.apply("a\r\nb\r\n\r"), "a\nb\n\r");
+    }
+
+    #[test]
+    fn regex_replacement_refers_to_groups() {
+        let normalizer: Normalizer = r"regex pid (?<n>\d+) => pid <$n>".parse().unwrap();
+        assert_eq!(normalizer.apply("pid 42, pid 7"), "pid <42>, pid <7>");
+    }
+
+    #[test]
+    fn applies_normalizers_in_order() {
+        let normalizers = [
+            "line-endings".parse().unwrap(),
+            "regex 0x<address> => PTR".parse().unwrap(),
+            Normalizer::HexAddresses,
+        ];
+        assert_eq!(normalize("0x10\r\n", &normalizers), "0x<address>\n");
+        assert_eq!(
+            normalize(
+                "0x10\r\n",
+                &[normalizers[2].clone(), normalizers[1].clone()]
+            ),
+            "PTR\r\n"
+        );
+    }
+
+    #[test]
+    fn parses_directives() {
+        assert_eq!(
+            "trim-trailing-whitespace".parse(),
+            Ok(Normalizer::TrimTrailingWhitespace)
+        );
+        assert!("hex".parse::<Normalizer>().is_err());
+        assert!("regex ( => x".parse::<Normalizer>().is_err());
+        assert!("regex missing-arrow".parse::<Normalizer>().is_err());
+    }
+
+    #[test]
+    fn deserializes_from_config() {
+        let config: Config = toml::from_str(
+            r#"normalize = [
+                { kind = "hex-addresses" },
+                { kind = "regex", pattern = "t=\\d+", replacement = "t=<t>" },
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.normalize,
+            [
+                Normalizer::HexAddresses,
+                Normalizer::regex(r"t=\d+", "t=<t>".to_string()).unwrap(),
+            ]
+        );
+        assert!(toml::from_str::<Config>(
+            r#"normalize = [{ kind = "regex", pattern = "(", replacement = "" }]"#
+        )
+        .is_err());
+    }
+}
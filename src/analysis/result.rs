@@ -1,37 +1,130 @@
+use super::float::TokenMismatch;
+use super::termination::Termination;
 use colored::*;
 use similar::{ChangeTag, TextDiff};
 use std::fmt;
 
+/// A difference between the left binary, built by the compiler under test,
+/// and the right one. Which compilers those are is kept in the
+/// [`AnalysisResult`].
 #[derive(Debug, PartialEq)]
 pub enum Diff {
-    ExitCode(String, String),
+    /// The binaries finished in different ways, see [`Termination::is_equivalent`].
+    ExitCode(Termination, Termination),
     Stdout(String, String),
+    /// Stdout compared token by token with a float tolerance.
+    StdoutTokens(Vec<TokenMismatch>),
     Stderr(String, String),
     Timeout(bool, bool),
+    /// `compiler`'s stdout does not match the golden output.
+    ExpectedStdout {
+        compiler: String,
+        expected: String,
+        actual: String,
+    },
+    /// `compiler`'s exit code does not match the golden exit code.
+    ExpectedExitCode {
+        compiler: String,
+        expected: String,
+        actual: String,
+    },
+    /// A compile-fail test was accepted by at least one compiler. Each side
+    /// holds the diagnostics of the compiler if it rejected the program.
+    Rejection {
+        left: Option<String>,
+        right: Option<String>,
+    },
+    /// Both compilers rejected a compile-fail test, but `compiler` did not
+    /// report the expected errors.
+    ErrorMismatch {
+        compiler: String,
+        missing: Vec<String>,
+        diagnostics: String,
+    },
 }
 
-impl fmt::Display for Diff {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+impl Diff {
+    /// Short, stable name of the kind of difference. `left-accepts` means
+    /// the left compiler accepts a program the right one rejects.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Diff::ExitCode(..) => "exit-code",
+            Diff::Stdout(..) => "stdout",
+            Diff::StdoutTokens(..) => "stdout-tokens",
+            Diff::Stderr(..) => "stderr",
+            Diff::Timeout(..) => "timeout",
+            Diff::ExpectedStdout { .. } => "expected-stdout",
+            Diff::ExpectedExitCode { .. } => "expected-exit-code",
+            Diff::Rejection { left, right } => match (left, right) {
+                (None, Some(_)) => "left-accepts",
+                (Some(_), None) => "right-accepts",
+                _ => "both-accept",
+            },
+            Diff::ErrorMismatch { .. } => "error-mismatch",
+        }
+    }
+}
+
+impl Diff {
+    /// Renders the difference with `left` and `right` as the names of the
+    /// compilers.
+    fn write(&self, f: &mut fmt::Formatter<'_>, [left, right]: &[String; 2]) -> fmt::Result {
         match self {
-            Diff::Timeout(gccrs_timed_out, rustc_timed_out) => {
+            Diff::Timeout(left_timed_out, right_timed_out) => {
                 writeln!(f, "{}", "=== Timeout Diff ===".bold())?;
                 writeln!(
                     f,
-                    "gccrs timed out: {}\nrustc timed out: {}",
-                    gccrs_timed_out, rustc_timed_out
+                    "{} timed out: {}\n{} timed out: {}",
+                    left, left_timed_out, right, right_timed_out
                 )?;
             }
-            Diff::ExitCode(gccrs_exit, rustc_exit) => {
+            Diff::ExitCode(left_exit, right_exit) => {
                 writeln!(f, "\n{}", "=== Exit Code Diff ===".bold())?;
-                writeln!(f, "gccrs: {}\nrustc: {}", gccrs_exit, rustc_exit)?;
+                writeln!(f, "{}: {}\n{}: {}", left, left_exit, right, right_exit)?;
             }
-            Diff::Stdout(gccrs_stdout, rustc_stdout) => {
+            Diff::Stdout(left_stdout, right_stdout) => {
                 writeln!(f, "{}", "=== Stdout Diff ===".bold())?;
-                print_diff(f, gccrs_stdout, rustc_stdout)?;
+                print_diff(f, left_stdout, right_stdout, left, right)?;
             }
-            Diff::Stderr(gccrs_stderr, rustc_stderr) => {
+            Diff::StdoutTokens(mismatches) => {
+                writeln!(f, "{}", "=== Stdout Diff (float tolerance) ===".bold())?;
+                write!(f, "{}", format_mismatches(mismatches, [left, right]))?;
+            }
+            Diff::Stderr(left_stderr, right_stderr) => {
                 writeln!(f, "{}", "=== Stderr Diff ===".bold())?;
-                print_diff(f, gccrs_stderr, rustc_stderr)?;
+                print_diff(f, left_stderr, right_stderr, left, right)?;
+            }
+            Diff::ExpectedStdout {
+                compiler,
+                expected,
+                actual,
+            } => {
+                let title = format!("=== Stdout Diff ({} vs expected) ===", compiler);
+                writeln!(f, "{}", title.bold())?;
+                print_diff(f, actual, expected, compiler, "expected")?;
+            }
+            Diff::ExpectedExitCode {
+                compiler,
+                expected,
+                actual,
+            } => {
+                let title = format!("=== Exit Code Diff ({} vs expected) ===", compiler);
+                writeln!(f, "\n{}", title.bold())?;
+                writeln!(f, "{}: {}\nexpected: {}", compiler, actual, expected)?;
+            }
+            Diff::Rejection { .. } => {
+                writeln!(f, "\n{}", "=== Compile-Fail Diff ===".bold())?;
+                write!(f, "{}", format_rejection(self, [left, right]))?;
+            }
+            Diff::ErrorMismatch {
+                compiler,
+                missing,
+                diagnostics,
+            } => {
+                let title = format!("=== Error Diff ({} vs expected) ===", compiler);
+                writeln!(f, "\n{}", title.bold())?;
+                writeln!(f, "missing: {}", missing.join(", "))?;
+                write!(f, "{}", diagnostics)?;
             }
         }
         Ok(())
@@ -41,6 +134,8 @@ impl fmt::Display for Diff {
 #[derive(Debug, PartialEq)]
 pub struct AnalysisResult {
     pub differences: Vec<Diff>,
+    /// Names of the compilers of the left and right side of each difference.
+    pub compilers: [String; 2],
 }
 
 impl AnalysisResult {
@@ -57,7 +152,8 @@ impl fmt::Display for AnalysisResult {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.has_diff() {
             for diff in &self.differences {
-                writeln!(f, "{}", diff)?;
+                diff.write(f, &self.compilers)?;
+                writeln!(f)?;
             }
         } else {
             writeln!(f, "No differences found. The results are identical.")?;
@@ -66,15 +162,81 @@ impl fmt::Display for AnalysisResult {
     }
 }
 
-fn print_diff(f: &mut fmt::Formatter<'_>, rustc: &str, gccrs: &str) -> fmt::Result {
-    let diff = TextDiff::from_lines(rustc, gccrs);
+const MAX_LISTED_MISMATCHES: usize = 20;
+
+/// One line per mismatching token, so that the first differing number is
+/// easy to spot even in long outputs.
+pub fn format_mismatches(mismatches: &[TokenMismatch], [left, right]: [&str; 2]) -> String {
+    let token = |token: &Option<String>| {
+        token
+            .as_ref()
+            .map_or_else(|| "<end of output>".to_string(), |t| format!("`{}`", t))
+    };
+
+    let mut text = String::new();
+    for mismatch in mismatches.iter().take(MAX_LISTED_MISMATCHES) {
+        text.push_str(&format!(
+            "line {:4}: {} {}, {} {}\n",
+            mismatch.line,
+            left,
+            token(&mismatch.left),
+            right,
+            token(&mismatch.right)
+        ));
+    }
+    if mismatches.len() > MAX_LISTED_MISMATCHES {
+        text.push_str(&format!(
+            "... and {} more mismatching token(s)\n",
+            mismatches.len() - MAX_LISTED_MISMATCHES
+        ));
+    }
+    text
+}
+
+/// Which compiler accepted a compile-fail test, followed by the diagnostics
+/// of the one that rejected it.
+pub fn format_rejection(diff: &Diff, [left_name, right_name]: [&str; 2]) -> String {
+    let Diff::Rejection { left, right } = diff else {
+        return String::new();
+    };
+    let outcome = |diagnostics: &Option<String>| match diagnostics {
+        Some(_) => "rejected",
+        None => "accepted",
+    };
+
+    let mut text = format!(
+        "{}: {}\n{}: {}\n",
+        left_name,
+        outcome(left),
+        right_name,
+        outcome(right)
+    );
+    for (compiler, diagnostics) in [(left_name, left), (right_name, right)] {
+        if let Some(diagnostics) = diagnostics {
+            text.push_str(&format!(
+                "--- {} diagnostics ---\n{}",
+                compiler, diagnostics
+            ));
+        }
+    }
+    text
+}
+
+fn print_diff(
+    f: &mut fmt::Formatter<'_>,
+    old: &str,
+    new: &str,
+    old_label: &str,
+    new_label: &str,
+) -> fmt::Result {
+    let diff = TextDiff::from_lines(old, new);
 
     for group in diff.grouped_ops(3).iter() {
         writeln!(
             f,
             "Legend: {}, {}",
-            "- gccrs".to_string().red(),
-            "+ rustc".to_string().green()
+            format!("- {}", old_label).red(),
+            format!("+ {}", new_label).green()
         )?;
 
         for op in group {
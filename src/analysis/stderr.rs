@@ -0,0 +1,146 @@
+use regex::Regex;
+use serde_derive::Deserialize;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// How stderr of the two binaries is compared.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StderrMode {
+    Ignore,
+    /// Only whether each side wrote anything at all.
+    #[default]
+    Presence,
+    Exact,
+    /// Exact after applying the output normalizers.
+    Normalized,
+    /// Only the message of a panic, and its line if both binaries were built
+    /// from the same lines, ignoring how each runtime formats it and
+    /// anything else written to stderr.
+    PanicAware,
+}
+
+impl FromStr for StderrMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "ignore" => Ok(Self::Ignore),
+            "presence" => Ok(Self::Presence),
+            "exact" => Ok(Self::Exact),
+            "normalized" => Ok(Self::Normalized),
+            "panic-aware" => Ok(Self::PanicAware),
+            _ => Err(format!("unknown stderr mode '{}'", value)),
+        }
+    }
+}
+
+/// The parts of a panic report that have to agree between the runtimes.
+#[derive(Debug, PartialEq)]
+pub struct PanicInfo {
+    pub message: String,
+    /// `line:column` of the panic. The file is left out since each compiler
+    /// builds its own copy of the source.
+    pub location: Option<String>,
+}
+
+/// Finds the panic reported in `stderr`, in either the current
+/// `panicked at <file>:<line>:<col>:\n<message>` format or the older
+/// `panicked at '<message>', <file>:<line>:<col>` one.
+pub fn extract_panic(stderr: &str) -> Option<PanicInfo> {
+    static CURRENT: OnceLock<Regex> = OnceLock::new();
+    static LEGACY: OnceLock<Regex> = OnceLock::new();
+    let current = CURRENT.get_or_init(|| {
+        Regex::new(r"panicked at [^\n]*?:(\d+:\d+):\n((?s).*?)(?:\nnote: |\nstack backtrace:|\z)")
+            .unwrap()
+    });
+    let legacy =
+        LEGACY.get_or_init(|| Regex::new(r"panicked at '((?s).*?)', [^\n]*?:(\d+:\d+)").unwrap());
+
+    if let Some(captures) = current.captures(stderr) {
+        return Some(PanicInfo {
+            message: captures[2].trim_end().to_string(),
+            location: Some(captures[1].to_string()),
+        });
+    }
+    if let Some(captures) = legacy.captures(stderr) {
+        return Some(PanicInfo {
+            message: captures[1].to_string(),
+            location: Some(captures[2].to_string()),
+        });
+    }
+    stderr.lines().find_map(|line| {
+        let (_, message) = line.split_once("panicked")?;
+        Some(PanicInfo {
+            message: message
+                .trim_start_matches([' ', ':'])
+                .trim_end()
+                .to_string(),
+            location: None,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn panic(message: &str, location: Option<&str>) -> Option<PanicInfo> {
+        Some(PanicInfo {
+            message: message.to_string(),
+            location: location.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn current_format() {
+        let stderr = "\nthread 'main' panicked at src/main.rs:4:5:\nindex out of bounds: the len is 3 but the index is 7\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n";
+        assert_eq!(
+            extract_panic(stderr),
+            panic(
+                "index out of bounds: the len is 3 but the index is 7",
+                Some("4:5")
+            )
+        );
+    }
+
+    #[test]
+    fn current_format_with_multiline_message() {
+        let stderr = "thread 'main' panicked at /tmp/x/gccrs.rs:10:9:\nfirst\nsecond\nstack backtrace:\n   0: main\n";
+        assert_eq!(extract_panic(stderr), panic("first\nsecond", Some("10:9")));
+        assert_eq!(
+            extract_panic("thread 'main' panicked at a.rs:1:2:\nboom"),
+            panic("boom", Some("1:2"))
+        );
+    }
+
+    #[test]
+    fn legacy_format() {
+        let stderr = "thread 'main' panicked at 'attempt to add with overflow', src/main.rs:3:13\nnote: run with `RUST_BACKTRACE=1`\n";
+        assert_eq!(
+            extract_panic(stderr),
+            panic("attempt to add with overflow", Some("3:13"))
+        );
+    }
+
+    #[test]
+    fn fallback_takes_the_rest_of_the_line() {
+        assert_eq!(
+            extract_panic("warning: x\nfatal runtime: panicked: out of memory \n"),
+            panic("out of memory", None)
+        );
+    }
+
+    #[test]
+    fn no_panic() {
+        assert_eq!(extract_panic(""), None);
+        assert_eq!(extract_panic("Segmentation fault\n"), None);
+    }
+
+    #[test]
+    fn parses_stderr_mode() {
+        assert_eq!("panic-aware".parse(), Ok(StderrMode::PanicAware));
+        assert_eq!("ignore".parse(), Ok(StderrMode::Ignore));
+        assert!("panic".parse::<StderrMode>().is_err());
+    }
+}
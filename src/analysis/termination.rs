@@ -0,0 +1,209 @@
+use super::limits::Resource;
+use std::fmt;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+/// Exit code of a Rust program that unwound out of `main` after a panic.
+const PANIC_EXIT_CODE: i32 = 101;
+
+/// Broad class of how a binary finished. Runs in different categories are
+/// never equivalent, whatever their exact codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminationKind {
+    CleanExit,
+    /// Unwound with the panic exit code, or aborted with `SIGABRT`. The two
+    /// runtimes may use different panic strategies, so both count as one.
+    PanicOrAbort,
+    /// Killed by any other signal, such as `SIGSEGV`.
+    Crash,
+    TimedOut,
+    /// Ended by one of the configured resource limits.
+    LimitExceeded(Resource),
+}
+
+/// How a binary finished, with the exact exit code or signal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Termination {
+    pub kind: TerminationKind,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub core_dumped: bool,
+}
+
+impl TerminationKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TerminationKind::CleanExit => "clean exit",
+            TerminationKind::PanicOrAbort => "panic or abort",
+            TerminationKind::Crash => "crash",
+            TerminationKind::TimedOut => "timed out",
+            TerminationKind::LimitExceeded(_) => "resource limit exceeded",
+        }
+    }
+}
+
+impl Termination {
+    pub fn from_status(status: ExitStatus) -> Self {
+        let exit_code = status.code();
+        let signal = status.signal();
+        let kind = match (exit_code, signal) {
+            (Some(PANIC_EXIT_CODE), _) | (_, Some(libc::SIGABRT)) => TerminationKind::PanicOrAbort,
+            (Some(_), _) => TerminationKind::CleanExit,
+            _ => TerminationKind::Crash,
+        };
+        Self {
+            kind,
+            exit_code,
+            signal,
+            core_dumped: status.core_dumped(),
+        }
+    }
+
+    pub fn timed_out() -> Self {
+        Self {
+            kind: TerminationKind::TimedOut,
+            exit_code: None,
+            signal: None,
+            core_dumped: false,
+        }
+    }
+
+    /// Whether two runs finished the same way. Exit codes and signals only
+    /// have to agree within the clean exit and crash categories.
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        match (self.kind, other.kind) {
+            (TerminationKind::CleanExit, TerminationKind::CleanExit) => {
+                self.exit_code == other.exit_code
+            }
+            (TerminationKind::Crash, TerminationKind::Crash) => self.signal == other.signal,
+            (kind, other_kind) => kind == other_kind,
+        }
+    }
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind.name())?;
+        if let TerminationKind::LimitExceeded(resource) = self.kind {
+            write!(f, ": {}", resource.name())?;
+        }
+        match (self.exit_code, self.signal) {
+            (Some(code), _) => write!(f, " (exit code {})", code)?,
+            (None, Some(signal)) => {
+                write!(f, " (signal {}", signal)?;
+                if let Some(name) = signal_name(signal) {
+                    write!(f, ", {}", name)?;
+                }
+                if self.core_dumped {
+                    write!(f, ", core dumped")?;
+                }
+                write!(f, ")")?;
+            }
+            (None, None) => {}
+        }
+        Ok(())
+    }
+}
+
+pub fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGSYS => "SIGSYS",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exited(code: i32) -> Termination {
+        Termination::from_status(ExitStatus::from_raw(code << 8))
+    }
+
+    fn killed(signal: i32) -> Termination {
+        Termination::from_status(ExitStatus::from_raw(signal))
+    }
+
+    #[test]
+    fn classifies_exit_codes() {
+        assert_eq!(
+            exited(0),
+            Termination {
+                kind: TerminationKind::CleanExit,
+                exit_code: Some(0),
+                signal: None,
+                core_dumped: false,
+            }
+        );
+        assert_eq!(exited(3).kind, TerminationKind::CleanExit);
+        assert_eq!(exited(PANIC_EXIT_CODE).kind, TerminationKind::PanicOrAbort);
+    }
+
+    #[test]
+    fn classifies_signals() {
+        assert_eq!(
+            killed(libc::SIGABRT),
+            Termination {
+                kind: TerminationKind::PanicOrAbort,
+                exit_code: None,
+                signal: Some(libc::SIGABRT),
+                core_dumped: false,
+            }
+        );
+        assert_eq!(killed(libc::SIGSEGV).kind, TerminationKind::Crash);
+        assert_eq!(killed(libc::SIGXCPU).kind, TerminationKind::Crash);
+        // The core dump flag of the wait status.
+        assert!(Termination::from_status(ExitStatus::from_raw(libc::SIGSEGV | 0x80)).core_dumped);
+    }
+
+    #[test]
+    fn clean_exits_need_the_same_code() {
+        assert!(exited(0).is_equivalent(&exited(0)));
+        assert!(!exited(0).is_equivalent(&exited(1)));
+    }
+
+    #[test]
+    fn panics_and_aborts_are_equivalent() {
+        assert!(exited(PANIC_EXIT_CODE).is_equivalent(&killed(libc::SIGABRT)));
+        assert!(!exited(PANIC_EXIT_CODE).is_equivalent(&exited(1)));
+    }
+
+    #[test]
+    fn crashes_need_the_same_signal() {
+        assert!(killed(libc::SIGSEGV).is_equivalent(&killed(libc::SIGSEGV)));
+        assert!(!killed(libc::SIGSEGV).is_equivalent(&killed(libc::SIGXCPU)));
+        assert!(!killed(libc::SIGSEGV).is_equivalent(&killed(libc::SIGABRT)));
+    }
+
+    #[test]
+    fn timeouts_only_match_timeouts() {
+        assert!(Termination::timed_out().is_equivalent(&Termination::timed_out()));
+        assert!(!Termination::timed_out().is_equivalent(&killed(libc::SIGKILL)));
+        assert!(!Termination::timed_out().is_equivalent(&exited(0)));
+    }
+
+    #[test]
+    fn displays_code_or_signal() {
+        assert_eq!(exited(2).to_string(), "clean exit (exit code 2)");
+        assert_eq!(
+            killed(libc::SIGSEGV).to_string(),
+            format!("crash (signal {}, SIGSEGV)", libc::SIGSEGV)
+        );
+        assert_eq!(Termination::timed_out().to_string(), "timed out");
+    }
+}
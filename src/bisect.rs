@@ -0,0 +1,461 @@
+use crate::analysis::AnalysisError;
+use crate::compiler::CompilerKind;
+use crate::reporting::Report;
+use crate::session::Session;
+use crate::testsuite::{TestCase, TestSuiteError};
+use crate::workspace::{BuildWorkspace, WorkspaceError};
+use log::{info, warn};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BisectError {
+    #[error("git {args} failed: {message}")]
+    Git { args: String, message: String },
+
+    #[error("Building {revision} failed: {message}")]
+    Build { revision: String, message: String },
+
+    #[error("Need at least two builds to bisect, found {0}")]
+    TooFewBuilds(usize),
+
+    #[error("Cannot bisect: {0}")]
+    Endpoint(String),
+
+    #[error("No compiler of the gccrs dialect in the config file to bisect")]
+    NoGccrs,
+
+    #[error(transparent)]
+    TestSuite(#[from] TestSuiteError),
+
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+}
+
+/// The gccrs builds to bisect over, from the oldest to the newest.
+pub enum Builds {
+    /// Prebuilt binaries.
+    Binaries(Vec<PathBuf>),
+    /// Revisions of a git checkout, built on demand.
+    Git(GitBuilds),
+}
+
+pub struct GitBuilds {
+    repo: PathBuf,
+    build_command: String,
+    binary: PathBuf,
+    /// The good revision followed by every first-parent commit up to the bad one.
+    revisions: Vec<String>,
+    /// What was checked out before bisecting, restored on drop.
+    original: String,
+}
+
+impl Builds {
+    pub fn count(&self) -> usize {
+        match self {
+            Builds::Binaries(binaries) => binaries.len(),
+            Builds::Git(git) => git.revisions.len(),
+        }
+    }
+
+    pub fn label(&self, index: usize) -> String {
+        match self {
+            Builds::Binaries(binaries) => binaries[index].display().to_string(),
+            Builds::Git(git) => git.revisions[index].clone(),
+        }
+    }
+
+    /// Returns the gccrs binary of build `index`, building it first if needed.
+    pub fn prepare(&self, index: usize) -> Result<PathBuf, BisectError> {
+        match self {
+            Builds::Binaries(binaries) => Ok(binaries[index].clone()),
+            Builds::Git(git) => git.build(index),
+        }
+    }
+}
+
+impl GitBuilds {
+    pub fn new(
+        repo: &Path,
+        good: &str,
+        bad: &str,
+        build_command: String,
+        binary: &Path,
+    ) -> Result<Self, BisectError> {
+        let original = match git(repo, &["symbolic-ref", "--quiet", "--short", "HEAD"]) {
+            Ok(branch) => branch,
+            Err(_) => git(repo, &["rev-parse", "HEAD"])?,
+        };
+        let good = git(repo, &["rev-parse", good])?;
+        let range = format!("{}..{}", good, bad);
+        let mut revisions = vec![good];
+        revisions.extend(
+            git(repo, &["rev-list", "--reverse", "--first-parent", &range])?
+                .lines()
+                .map(str::to_string),
+        );
+
+        Ok(Self {
+            repo: repo.to_path_buf(),
+            build_command,
+            binary: repo.join(binary),
+            revisions,
+            original,
+        })
+    }
+
+    fn build(&self, index: usize) -> Result<PathBuf, BisectError> {
+        let revision = &self.revisions[index];
+        git(&self.repo, &["checkout", "--quiet", "--detach", revision])?;
+        info!("Building gccrs at {}", revision);
+
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&self.build_command)
+            .current_dir(&self.repo)
+            .output()
+            .map_err(|e| BisectError::Build {
+                revision: revision.clone(),
+                message: e.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(BisectError::Build {
+                revision: revision.clone(),
+                message: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        Ok(self.binary.clone())
+    }
+}
+
+impl Drop for GitBuilds {
+    fn drop(&mut self) {
+        if let Err(e) = git(&self.repo, &["checkout", "--quiet", &self.original]) {
+            warn!("Could not restore the checkout to {}: {}", self.original, e);
+        }
+    }
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String, BisectError> {
+    let error = |message: String| BisectError::Git {
+        args: args.join(" "),
+        message,
+    };
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .map_err(|e| error(e.to_string()))?;
+    if !output.status.success() {
+        return Err(error(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// How a test case fared with one build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+    /// The build could not be made, or the test case could not be built or
+    /// run with it, so it tells nothing either way.
+    Skip,
+}
+
+/// Where the first failing build is, as far as the builds that could be
+/// tested tell.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Bisection {
+    pub last_passing: usize,
+    pub first_failing: usize,
+    /// Skipped builds between the two, any of which may be the first
+    /// failing one.
+    pub untested: Vec<usize>,
+}
+
+/// Only differences between the binaries count as a failure. A build with
+/// which the test case does not compile, crashes the compiler or times out
+/// says nothing about the bug being bisected and is skipped.
+fn verdict(passed: bool, report: &Report) -> Verdict {
+    let differs = report
+        .analysis_reports
+        .iter()
+        .any(|result| matches!(result.result, Err(AnalysisError::ComparisonFailed(..))));
+    match (passed, differs) {
+        (true, _) => Verdict::Pass,
+        (false, true) => Verdict::Fail,
+        (false, false) => Verdict::Skip,
+    }
+}
+
+/// Binary search for the first of `count` builds that `test` fails, given
+/// that the first build passes and the last one fails. Like `git bisect
+/// skip`, a skipped build is replaced by the untested build closest to it.
+pub fn first_failing<E, F>(count: usize, mut test: F) -> Result<Bisection, E>
+where
+    F: FnMut(usize) -> Result<Verdict, E>,
+{
+    let (mut good, mut bad) = (0, count - 1);
+    let mut skipped = BTreeSet::new();
+    loop {
+        let middle = good + (bad - good) / 2;
+        let Some(candidate) = (good + 1..bad)
+            .filter(|index| !skipped.contains(index))
+            .min_by_key(|index| index.abs_diff(middle))
+        else {
+            break;
+        };
+        match test(candidate)? {
+            Verdict::Pass => good = candidate,
+            Verdict::Fail => bad = candidate,
+            Verdict::Skip => {
+                skipped.insert(candidate);
+            }
+        }
+    }
+    Ok(Bisection {
+        last_passing: good,
+        first_failing: bad,
+        untested: skipped.range(good + 1..bad).copied().collect(),
+    })
+}
+
+/// Binary-searches `builds` for the first gccrs with which the test case
+/// fails, compiling and comparing it with each build it visits. The builds
+/// replace the program of the first gccrs-dialect compiler.
+pub fn bisect(
+    session: &Session,
+    source: &Path,
+    gccrs: Option<&Path>,
+    builds: &Builds,
+) -> Result<Bisection, BisectError> {
+    if builds.count() < 2 {
+        return Err(BisectError::TooFewBuilds(builds.count()));
+    }
+    let name = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| TestSuiteError::InvalidPath(source.display().to_string()))?
+        .to_string();
+    let case = match gccrs {
+        Some(gccrs) => TestCase::from_pair(name, source.to_path_buf(), gccrs.to_path_buf())?,
+        None => TestCase::from_source(name, source)?,
+    };
+
+    let bisected = session
+        .backends
+        .iter()
+        .position(|backend| backend.dialect == CompilerKind::Gccrs)
+        .ok_or(BisectError::NoGccrs)?;
+    let test = |index: usize| -> Result<Verdict, BisectError> {
+        let program = match builds.prepare(index) {
+            Ok(program) => program,
+            Err(e @ BisectError::Build { .. }) => {
+                warn!("Skipping {}: {}", builds.label(index), e);
+                return Ok(Verdict::Skip);
+            }
+            Err(e) => return Err(e),
+        };
+        let mut backends = session.backends.clone();
+        backends[bisected].set_program(&program);
+        let step = Session {
+            config: session.config,
+            workspace: BuildWorkspace::new(session.workspace.root())?,
+            backends,
+            profiles: session.profiles.clone(),
+            bless: false,
+            self_diff: session.self_diff,
+        };
+        let workspace = step.workspace.for_case(&case)?;
+        let mut report = Report::new();
+        let passed = step.analyze_case(&case, &workspace, &mut report);
+        workspace.cleanup()?;
+
+        let verdict = verdict(passed, &report);
+        match verdict {
+            Verdict::Skip => warn!(
+                "Skipping {}: {}",
+                builds.label(index),
+                report.errors.first().map_or_else(
+                    || "the test case could not be run".to_string(),
+                    ToString::to_string
+                )
+            ),
+            _ => info!(
+                "'{}' {} with {}",
+                case.name,
+                if passed { "passes" } else { "fails" },
+                builds.label(index)
+            ),
+        }
+        Ok(verdict)
+    };
+
+    let last = builds.count() - 1;
+    let endpoint = |message: String| Err(BisectError::Endpoint(message));
+    match test(0)? {
+        Verdict::Pass => {}
+        Verdict::Fail => {
+            return endpoint(format!(
+                "'{}' already fails with the first build {}",
+                case.name,
+                builds.label(0)
+            ))
+        }
+        Verdict::Skip => return endpoint(format!("cannot build {}", builds.label(0))),
+    }
+    match test(last)? {
+        Verdict::Fail => {}
+        Verdict::Pass => {
+            return endpoint(format!(
+                "'{}' passes with the last build {}",
+                case.name,
+                builds.label(last)
+            ))
+        }
+        Verdict::Skip => return endpoint(format!("cannot build {}", builds.label(last))),
+    }
+
+    let bisection = first_failing(builds.count(), test)?;
+    if bisection.untested.is_empty() {
+        info!(
+            "First failing build: {} (last passing: {})",
+            builds.label(bisection.first_failing),
+            builds.label(bisection.last_passing)
+        );
+    } else {
+        let candidates: Vec<_> = bisection
+            .untested
+            .iter()
+            .chain([&bisection.first_failing])
+            .map(|index| builds.label(*index))
+            .collect();
+        info!(
+            "First failing build is one of {} (last passing: {}), the others could not be tested",
+            candidates.join(", "),
+            builds.label(bisection.last_passing)
+        );
+    }
+    Ok(bisection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{AnalysisResult, Diff, ExecutionError};
+    use crate::reporting::ErrorReporter;
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    /// Bisects builds that fail from `first_bad` on, skipping `skipped`, and
+    /// returns the result with the builds that were tested.
+    fn run(count: usize, first_bad: usize, skipped: &[usize]) -> (Bisection, Vec<usize>) {
+        let mut tested = Vec::new();
+        let bisection = first_failing(count, |index| {
+            tested.push(index);
+            Ok::<_, Infallible>(if skipped.contains(&index) {
+                Verdict::Skip
+            } else if index >= first_bad {
+                Verdict::Fail
+            } else {
+                Verdict::Pass
+            })
+        })
+        .unwrap();
+        (bisection, tested)
+    }
+
+    fn found(last_passing: usize, first_failing: usize, untested: &[usize]) -> Bisection {
+        Bisection {
+            last_passing,
+            first_failing,
+            untested: untested.to_vec(),
+        }
+    }
+
+    #[test]
+    fn only_the_last_build_fails() {
+        assert_eq!(run(10, 9, &[]).0, found(8, 9, &[]));
+    }
+
+    #[test]
+    fn every_build_after_the_first_fails() {
+        assert_eq!(run(10, 1, &[]).0, found(0, 1, &[]));
+    }
+
+    #[test]
+    fn finds_a_single_boundary() {
+        for first_bad in 1..17 {
+            let (bisection, tested) = run(17, first_bad, &[]);
+            assert_eq!(bisection, found(first_bad - 1, first_bad, &[]));
+            assert!(tested.len() <= 4, "tested {:?}", tested);
+        }
+    }
+
+    #[test]
+    fn two_builds_need_no_tests() {
+        let (bisection, tested) = run(2, 1, &[]);
+        assert_eq!(bisection, found(0, 1, &[]));
+        assert!(tested.is_empty());
+    }
+
+    #[test]
+    fn skipped_builds_next_to_the_boundary() {
+        assert_eq!(run(10, 5, &[4]).0, found(3, 5, &[4]));
+        assert_eq!(run(10, 5, &[5]).0, found(4, 6, &[5]));
+        assert_eq!(run(10, 5, &[5, 6]).0, found(4, 7, &[5, 6]));
+    }
+
+    #[test]
+    fn skipped_builds_away_from_the_boundary_do_not_matter() {
+        assert_eq!(run(10, 7, &[2, 3, 4]).0, found(6, 7, &[]));
+    }
+
+    #[test]
+    fn every_build_skipped() {
+        let skipped: Vec<_> = (1..9).collect();
+        let (bisection, tested) = run(10, 5, &skipped);
+        assert_eq!(bisection, found(0, 9, &skipped));
+        assert_eq!(tested.len(), skipped.len());
+    }
+
+    fn report(result: Option<Result<(), AnalysisError>>, error: bool) -> Report {
+        let mut report = Report::new();
+        if let Some(result) = result {
+            report.add_result("case", "debug", ["debug"; 2], result, Duration::ZERO);
+        }
+        if error {
+            report.add_error(ErrorReporter::CompilerTimeout {
+                case: "case".to_string(),
+                compiler: "gccrs".to_string(),
+                profile: "debug".to_string(),
+                timeout: Duration::from_secs(1),
+            });
+        }
+        report
+    }
+
+    #[test]
+    fn only_differences_fail_a_build() {
+        let differs = Err(AnalysisError::ComparisonFailed(
+            AnalysisResult {
+                differences: vec![Diff::Timeout(true, false)],
+                compilers: ["gccrs".to_string(), "rustc".to_string()],
+            },
+            "case".to_string(),
+        ));
+        let unrunnable = Err(ExecutionError::Failed("no binary".to_string()).into());
+
+        assert_eq!(verdict(true, &report(Some(Ok(())), false)), Verdict::Pass);
+        assert_eq!(verdict(false, &report(Some(differs), false)), Verdict::Fail);
+        assert_eq!(verdict(false, &report(None, true)), Verdict::Skip);
+        assert_eq!(
+            verdict(false, &report(Some(unrunnable), false)),
+            Verdict::Skip
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use semantic_equivalence_validator::compiler::Optimization;
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -7,9 +8,49 @@ pub struct Cli {
     #[command(subcommand)]
     pub mode: Mode,
 
-    /// Run only with optmizations turned off (equivalent to Optimization::Zero)
+    /// Run only with optmizations turned off (equivalent to Optimization::Zero),
+    /// ignoring any build profiles from the config file
     #[arg(long, default_value_t = false)]
     pub no_opt: bool,
+
+    /// Directory under which each test case gets its own build directory.
+    /// Artifacts of failing cases are kept there for inspection
+    #[arg(long, default_value = "out")]
+    pub build_dir: PathBuf,
+
+    /// Number of test cases to compile and analyze in parallel
+    #[arg(short, long, default_value_t = 1)]
+    pub jobs: usize,
+
+    /// Write the golden `.expected` files of every test case from the output of the reference compiler
+    #[arg(long, default_value_t = false)]
+    pub bless: bool,
+
+    /// Format of the report
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub report_format: ReportFormat,
+
+    /// Write the report to this file instead of stdout (machine-readable formats only)
+    #[arg(long)]
+    pub report_file: Option<PathBuf>,
+
+    /// Baseline of known failures. Only failures it does not list make the run fail
+    #[arg(long)]
+    pub baseline: Option<PathBuf>,
+
+    /// Rewrite the baseline from the failures of this run instead of checking against it
+    #[arg(long, default_value_t = false, requires = "baseline")]
+    pub update_baseline: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    /// Colored summary on the terminal
+    Text,
+    /// JSON document with every result and error
+    Json,
+    /// JUnit XML with one testsuite per test case
+    Junit,
 }
 
 #[derive(Subcommand)]
@@ -27,4 +68,108 @@ pub enum Mode {
         /// Path to the root directory containing "rustc" and "gccrs" subdirectories
         path: PathBuf,
     },
+    /// Validate single source files that are built with both compilers
+    Source {
+        /// Path to a rust source file, or a directory of them
+        path: PathBuf,
+    },
+    /// Compare the binaries one compiler builds at each optimization level
+    /// against its -O0 binary, to find optimizer bugs
+    SelfDiff {
+        /// Path to a rust source file, or a directory of them. Only the
+        /// variant for the compiler's dialect has to build
+        path: PathBuf,
+
+        /// Name of the compiler to check [default: the first gccrs]
+        #[arg(long)]
+        compiler: Option<String>,
+    },
+    /// Minimize a failing test case while it keeps showing the same differences
+    Reduce {
+        /// Path to the rust source file for rustc, or to a single-source test case
+        source: PathBuf,
+
+        /// Path to the rust source file for gccrs, when reducing a pair of files
+        gccrs: Option<PathBuf>,
+
+        /// Optimization level the failure shows up at
+        #[arg(long, default_value = "0")]
+        opt_level: Optimization,
+
+        /// Directory to write the reduced sources to [default: <build-dir>/reduced]
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Generate random programs and keep the ones that show differences
+    Fuzz {
+        /// Number of programs to generate
+        #[arg(long, default_value_t = 100)]
+        count: u64,
+
+        /// Seed of the first program, the following ones use the next seeds
+        /// [default: derived from the current time]
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Directory that programs showing differences are saved to as
+        /// single-source test cases
+        #[arg(long, default_value = "tests/fuzz")]
+        save_dir: PathBuf,
+    },
+    /// Find the first gccrs build with which a test case fails
+    Bisect {
+        /// Path to the rust source file for rustc, or to a single-source test case
+        source: PathBuf,
+
+        /// Path to the rust source file for gccrs, when bisecting a pair of files
+        gccrs: Option<PathBuf>,
+
+        /// Optimization level the failure shows up at
+        #[arg(long, default_value = "0")]
+        opt_level: Optimization,
+
+        /// gccrs binaries ordered from oldest to newest. The first has to pass and the last to fail
+        #[arg(long, num_args = 1.., required_unless_present = "repo", conflicts_with = "repo")]
+        builds: Vec<PathBuf>,
+
+        /// gccrs git checkout whose first-parent commits from --good to --bad are built
+        #[arg(long, requires_all = ["good", "bad", "build_command", "binary"])]
+        repo: Option<PathBuf>,
+
+        /// Revision with which the test case passes
+        #[arg(long)]
+        good: Option<String>,
+
+        /// Revision with which the test case fails
+        #[arg(long)]
+        bad: Option<String>,
+
+        /// Shell command that builds gccrs, run in the checkout
+        #[arg(long)]
+        build_command: Option<String>,
+
+        /// gccrs binary produced by the build command, relative to the checkout
+        #[arg(long)]
+        binary: Option<PathBuf>,
+    },
+    /// Show what changed between the JSON reports of two runs
+    CompareReports {
+        /// JSON report of the earlier run
+        old: PathBuf,
+
+        /// JSON report of the later run
+        new: PathBuf,
+
+        /// Factor by which a result has to slow down to count as a duration regression
+        #[arg(long, default_value_t = 1.5)]
+        slowdown: f64,
+
+        /// Ignore slowdowns of fewer seconds than this
+        #[arg(long, default_value_t = 0.1)]
+        min_slowdown_secs: f64,
+
+        /// Fail on duration regressions too, not only on newly failing results
+        #[arg(long, default_value_t = false)]
+        fail_on_slowdown: bool,
+    },
 }
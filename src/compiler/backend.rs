@@ -0,0 +1,233 @@
+use super::{BuildProfile, CompilerKind};
+use serde_derive::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+/// A compiler that test cases are built with, as described by a
+/// `[[compilers]]` entry of the config file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Backend {
+    /// Shown in reports and diffs, and used to name build artifacts.
+    pub name: String,
+
+    /// Program and arguments. `{src}` and `{out}` are replaced by the source
+    /// file and the binary to produce, an argument that is exactly `{flags}`
+    /// by the flags of the build profile.
+    pub command: Vec<String>,
+
+    /// Which source of a test case the compiler builds, and which flags of
+    /// the build profiles it takes.
+    pub dialect: CompilerKind,
+
+    /// Flags per optimization level, keyed by `0`, `1`, `2`, `3`, `s` and
+    /// `z`. Levels without an entry take the flags of the dialect.
+    #[serde(default)]
+    pub opt_flags: BTreeMap<String, Vec<String>>,
+
+    /// Code appended to single-source test cases.
+    #[serde(default)]
+    pub prelude: Option<String>,
+
+    /// Seconds each invocation may take, overriding `timeouts.compile`.
+    #[serde(default)]
+    pub compile_timeout: Option<u64>,
+}
+
+pub const SOURCE_PLACEHOLDER: &str = "{src}";
+pub const OUTPUT_PLACEHOLDER: &str = "{out}";
+pub const FLAGS_PLACEHOLDER: &str = "{flags}";
+
+/// Indices into `backends` of the compilers compared with each other: every
+/// compiler against the one named `reference`, or every pair without one.
+/// The first of each pair is shown as `-` in diffs, the second as `+`.
+pub fn comparison_pairs(backends: &[Backend], reference: Option<&str>) -> Vec<(usize, usize)> {
+    match reference.and_then(|name| backends.iter().position(|b| b.name == name)) {
+        Some(reference) => (0..backends.len())
+            .filter(|index| *index != reference)
+            .map(|index| (index, reference))
+            .collect(),
+        None => (0..backends.len())
+            .flat_map(|left| (left + 1..backends.len()).map(move |right| (left, right)))
+            .collect(),
+    }
+}
+
+/// The compiler named `name`, or by default the first one of the gccrs
+/// dialect, falling back to the first compiler.
+pub fn find_backend<'a>(backends: &'a [Backend], name: Option<&str>) -> Option<&'a Backend> {
+    match name {
+        Some(name) => backends.iter().find(|backend| backend.name == name),
+        None => backends
+            .iter()
+            .find(|backend| backend.dialect == CompilerKind::Gccrs)
+            .or(backends.first()),
+    }
+}
+
+impl Backend {
+    pub fn program(&self) -> &str {
+        self.command.first().map(String::as_str).unwrap_or_default()
+    }
+
+    /// Swaps the compiler binary while keeping its arguments, as done when
+    /// bisecting over builds.
+    pub fn set_program(&mut self, program: &Path) {
+        let program = program.to_string_lossy().into_owned();
+        match self.command.first_mut() {
+            Some(first) => *first = program,
+            None => self.command.push(program),
+        }
+    }
+
+    pub fn flags(&self, profile: &BuildProfile) -> Vec<String> {
+        profile
+            .optimization
+            .and_then(|level| self.opt_flags.get(level.as_str()))
+            .cloned()
+            .unwrap_or_else(|| profile.flags_for(self.dialect).to_vec())
+    }
+
+    /// The invocation building `source` into `binary` with `profile`.
+    pub fn command(&self, source: &Path, binary: &Path, profile: &BuildProfile) -> Command {
+        let source = source.to_string_lossy();
+        let binary = binary.to_string_lossy();
+
+        let mut args = Vec::new();
+        for arg in &self.command {
+            if arg == FLAGS_PLACEHOLDER {
+                args.extend(self.flags(profile));
+            } else {
+                args.push(
+                    arg.replace(SOURCE_PLACEHOLDER, &source)
+                        .replace(OUTPUT_PLACEHOLDER, &binary),
+                );
+            }
+        }
+
+        let mut command = Command::new(args.first().map(String::as_str).unwrap_or_default());
+        command.args(args.iter().skip(1));
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::Optimization;
+    use std::ffi::OsStr;
+
+    fn backend(name: &str, command: &[&str]) -> Backend {
+        Backend {
+            name: name.to_string(),
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+            dialect: CompilerKind::Gccrs,
+            opt_flags: BTreeMap::new(),
+            prelude: None,
+            compile_timeout: None,
+        }
+    }
+
+    fn profile(gccrs: &[&str]) -> BuildProfile {
+        BuildProfile {
+            name: "custom".to_string(),
+            rustc: Vec::new(),
+            gccrs: gccrs.iter().map(|flag| flag.to_string()).collect(),
+            optimization: None,
+        }
+    }
+
+    fn invocation(backend: &Backend, profile: &BuildProfile) -> Vec<String> {
+        let command = backend.command(Path::new("dir/case.rs"), Path::new("out/case"), profile);
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(OsStr::to_string_lossy)
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        let backend = backend(
+            "gccrs",
+            &[
+                "gccrs",
+                "{flags}",
+                "{src}",
+                "-o",
+                "{out}",
+                "--out-dir={out}.d",
+            ],
+        );
+        assert_eq!(
+            invocation(&backend, &profile(&["-O2", "-g"])),
+            [
+                "gccrs",
+                "-O2",
+                "-g",
+                "dir/case.rs",
+                "-o",
+                "out/case",
+                "--out-dir=out/case.d"
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_an_empty_flags_list() {
+        let backend = backend("gccrs", &["gccrs", "{flags}", "{src}", "-o", "{out}"]);
+        assert_eq!(
+            invocation(&backend, &profile(&[])),
+            ["gccrs", "dir/case.rs", "-o", "out/case"]
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders_and_embedded_flags() {
+        let backend = backend(
+            "gccrs",
+            &["gccrs", "{src}", "-o", "{out}", "{edition}", "-f{flags}"],
+        );
+        assert_eq!(
+            invocation(&backend, &profile(&["-O2"])),
+            [
+                "gccrs",
+                "dir/case.rs",
+                "-o",
+                "out/case",
+                "{edition}",
+                "-f{flags}"
+            ]
+        );
+    }
+
+    #[test]
+    fn prefers_opt_flags_of_the_compiler() {
+        let mut backend = backend("gccrs", &["gccrs", "{flags}", "{src}", "-o", "{out}"]);
+        backend.opt_flags.insert(
+            "2".to_string(),
+            vec!["-O2".to_string(), "-flto".to_string()],
+        );
+        assert_eq!(
+            backend.flags(&BuildProfile::from_optimization(Optimization::Two)),
+            ["-O2", "-flto"]
+        );
+        assert_eq!(
+            backend.flags(&BuildProfile::from_optimization(Optimization::Three)),
+            Optimization::Three.for_compiler(CompilerKind::Gccrs)
+        );
+    }
+
+    #[test]
+    fn pairs_compilers_with_the_reference_or_with_each_other() {
+        let backends = ["a", "b", "c"].map(|name| backend(name, &[]));
+        assert_eq!(comparison_pairs(&backends, Some("b")), [(0, 1), (2, 1)]);
+        assert_eq!(comparison_pairs(&backends, None), [(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(
+            comparison_pairs(&backends, Some("unknown")),
+            comparison_pairs(&backends, None)
+        );
+        assert!(comparison_pairs(&backends[..1], None).is_empty());
+    }
+}
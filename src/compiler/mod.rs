@@ -1,10 +1,24 @@
+mod backend;
 mod optimization;
+mod profile;
 
-use crate::AppError;
-use log::info;
+use crate::analysis::{kill_process_group, signal_name, spawn_capture, CapturedOutput};
+use crate::workspace::CaseWorkspace;
+pub use backend::{
+    comparison_pairs, find_backend, Backend, FLAGS_PLACEHOLDER, OUTPUT_PLACEHOLDER,
+    SOURCE_PLACEHOLDER,
+};
 pub use optimization::Optimization;
-use std::path::Path;
-use std::process::Command;
+pub use profile::BuildProfile;
+use serde_derive::Deserialize;
+use std::io;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use thiserror::Error;
+use wait_timeout::ChildExt;
 
 pub const OPTIMIZATION_LEVELS: [Optimization; 6] = [
     Optimization::Zero,
@@ -15,7 +29,42 @@ pub const OPTIMIZATION_LEVELS: [Optimization; 6] = [
     Optimization::Z,
 ];
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Error)]
+pub enum CompileError {
+    #[error("Compilation error for {compiler}:\n {message}")]
+    Rejected {
+        compiler: String,
+        /// What the compiler printed besides its diagnostics.
+        stdout: String,
+        message: String,
+    },
+
+    /// The compiler hit an internal error or was killed by a signal, which
+    /// says nothing about whether the program is valid.
+    #[error("{compiler} crashed with {profile}: {reason}\n {message}")]
+    Crashed {
+        compiler: String,
+        profile: String,
+        reason: String,
+        stdout: String,
+        message: String,
+    },
+
+    #[error("{compiler} did not finish compiling with {profile} within {timeout:?}")]
+    Timeout {
+        compiler: String,
+        profile: String,
+        timeout: Duration,
+    },
+
+    #[error("I/O error for '{file}': {error}")]
+    Io { file: PathBuf, error: io::Error },
+}
+
+/// The two families of test sources: `rustc` and `gccrs` files of a pair,
+/// and the `cfg(rustc)` and `cfg(gccrs)` variants of a single source.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum CompilerKind {
     Rustc,
     Gccrs,
@@ -30,45 +79,112 @@ impl CompilerKind {
     }
 }
 
+/// What a compiler printed while building one profile.
+#[derive(Debug, Clone, Default)]
+pub struct CompilerOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
 impl std::fmt::Display for CompilerKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.name())
     }
 }
 
-pub fn compile_with(
-    compiler: &Path,
+/// Exit code of the GCC driver when the compiler proper hit an internal error.
+const GCC_ICE_EXIT_CODE: i32 = 4;
+/// Exit code of rustc when it panicked.
+const RUSTC_ICE_EXIT_CODE: i32 = 101;
+
+/// Tells a compiler that crashed apart from one that rejected the program,
+/// returning how it crashed.
+fn crash_reason(status: ExitStatus, stderr: &str) -> Option<String> {
+    if let Some(signal) = status.signal() {
+        return Some(match signal_name(signal) {
+            Some(name) => format!("killed by signal {} ({})", signal, name),
+            None => format!("killed by signal {}", signal),
+        });
+    }
+    let code = status.code()?;
+    (matches!(code, GCC_ICE_EXIT_CODE | RUSTC_ICE_EXIT_CODE)
+        || stderr.contains("internal compiler error"))
+    .then(|| format!("internal compiler error (exit code {})", code))
+}
+
+/// Builds `src_file_path` with one profile and returns what the compiler
+/// printed. A rejected program is reported as [`CompileError::Rejected`]
+/// carrying the compiler's diagnostics.
+pub fn compile(
+    backend: &Backend,
     src_file_path: &Path,
-    args: &[String],
-    compiler_kind: CompilerKind,
-) -> Result<(), AppError> {
-    info!(
-        "Compiling '{}' with {}",
-        src_file_path.display(),
-        compiler_kind
-    );
-
-    for level in &OPTIMIZATION_LEVELS {
-        let binary_path = format!("out/{}_{}.out", compiler_kind.name(), level.as_str());
-        let output = Command::new(compiler)
-            .arg(src_file_path)
-            .args(args)
-            .args(level.for_compiler(compiler_kind))
-            .arg("-o")
-            .arg(&binary_path)
-            .output()
-            .map_err(|e| AppError::Io {
-                file: src_file_path.to_path_buf(),
-                error: e,
-            })?;
-
-        if !output.status.success() {
-            return Err(AppError::Compilation {
-                compiler: compiler_kind.name().to_string(),
-                message: String::from_utf8_lossy(&output.stderr).to_string(),
-            });
-        }
+    profile: &BuildProfile,
+    workspace: &CaseWorkspace,
+    timeout: Duration,
+) -> Result<CompilerOutput, CompileError> {
+    let binary_path = workspace.binary(&backend.name, profile);
+    let io_error = |error| CompileError::Io {
+        file: src_file_path.to_path_buf(),
+        error,
+    };
+    let mut child = backend
+        .command(src_file_path, &binary_path, profile)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // A driver such as gccrs hangs in a child like `crab1`, which a
+        // timeout has to end along with the driver.
+        .process_group(0)
+        .spawn()
+        .map_err(io_error)?;
+
+    // Diagnostics are drained while waiting so that a compiler with a lot
+    // to say cannot block on a full pipe and be mistaken for a hang.
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| spawn_capture(pipe, usize::MAX));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|pipe| spawn_capture(pipe, usize::MAX));
+
+    let Some(status) = child.wait_timeout(timeout).map_err(io_error)? else {
+        kill_process_group(&mut child).map_err(io_error)?;
+        return Err(CompileError::Timeout {
+            compiler: backend.name.clone(),
+            profile: profile.name.clone(),
+            timeout,
+        });
+    };
+
+    let read = |handle: Option<JoinHandle<io::Result<CapturedOutput>>>| match handle
+        .map(|handle| handle.join())
+    {
+        Some(Ok(Ok(captured))) => Some(String::from_utf8_lossy(&captured.bytes).to_string()),
+        _ => None,
+    };
+    let stdout = read(stdout).unwrap_or_default();
+    let stderr = read(stderr);
+
+    if let Some(reason) = crash_reason(status, stderr.as_deref().unwrap_or_default()) {
+        return Err(CompileError::Crashed {
+            compiler: backend.name.clone(),
+            profile: profile.name.clone(),
+            reason,
+            stdout,
+            message: stderr.unwrap_or_default(),
+        });
+    }
+    if !status.success() {
+        return Err(CompileError::Rejected {
+            compiler: backend.name.clone(),
+            stdout,
+            message: stderr.unwrap_or_else(|| format!("{} failed with {}", backend.name, status)),
+        });
     }
 
-    Ok(())
+    Ok(CompilerOutput {
+        stdout,
+        stderr: stderr.unwrap_or_default(),
+    })
 }
@@ -1,4 +1,5 @@
 use super::CompilerKind;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy)]
 pub enum Optimization {
@@ -29,3 +30,22 @@ impl Optimization {
         }
     }
 }
+
+impl FromStr for Optimization {
+    type Err = String;
+
+    fn from_str(level: &str) -> Result<Self, Self::Err> {
+        match level {
+            "0" => Ok(Self::Zero),
+            "1" => Ok(Self::One),
+            "2" => Ok(Self::Two),
+            "3" => Ok(Self::Three),
+            "s" => Ok(Self::S),
+            "z" => Ok(Self::Z),
+            _ => Err(format!(
+                "unknown optimization level '{}', expected one of 0, 1, 2, 3, s, z",
+                level
+            )),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use super::{CompilerKind, Optimization};
+use serde_derive::Deserialize;
+
+/// A named pair of flag sets, one per compiler dialect, that a test case is
+/// built and compared with.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BuildProfile {
+    pub name: String,
+    #[serde(default)]
+    pub rustc: Vec<String>,
+    #[serde(default)]
+    pub gccrs: Vec<String>,
+
+    /// Set for the default profiles, so that compilers with their own
+    /// `opt_flags` can pick the flags of the level.
+    #[serde(skip)]
+    pub optimization: Option<Optimization>,
+}
+
+impl BuildProfile {
+    pub fn from_optimization(level: Optimization) -> Self {
+        Self {
+            name: format!("opt-level={}", level.as_str()),
+            rustc: level.for_compiler(CompilerKind::Rustc),
+            gccrs: level.for_compiler(CompilerKind::Gccrs),
+            optimization: Some(level),
+        }
+    }
+
+    pub fn flags_for(&self, compiler: CompilerKind) -> &[String] {
+        match compiler {
+            CompilerKind::Rustc => &self.rustc,
+            CompilerKind::Gccrs => &self.gccrs,
+        }
+    }
+
+    /// The profile name with every character that is awkward in a file name
+    /// replaced by an underscore.
+    pub fn file_stem(&self) -> String {
+        self.name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+}
@@ -1,4 +1,10 @@
+use crate::analysis::{LintMapping, Normalizer, ResourceLimits, StderrMode};
+use crate::compiler::{
+    Backend, BuildProfile, CompilerKind, Optimization, FLAGS_PLACEHOLDER, OPTIMIZATION_LEVELS,
+    OUTPUT_PLACEHOLDER, SOURCE_PLACEHOLDER,
+};
 use serde_derive::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
@@ -15,19 +21,192 @@ pub enum ConfigError {
     Validation(String),
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct AppConfig {
-    pub rustc: CompilerConfig,
-    pub gccrs: CompilerConfig,
+    /// The classic pair of compilers, used when no `[[compilers]]` are
+    /// given. gccrs is compared against rustc.
+    #[serde(default)]
+    pub rustc: Option<CompilerConfig>,
+    #[serde(default)]
+    pub gccrs: Option<CompilerConfig>,
+
+    /// Any number of compilers, replacing `rustc` and `gccrs`.
+    #[serde(default)]
+    pub compilers: Vec<Backend>,
+
+    /// Build profiles to compare with. When empty every level of
+    /// `OPTIMIZATION_LEVELS` is used instead.
+    #[serde(default)]
+    pub profiles: Vec<BuildProfile>,
+
+    #[serde(default)]
+    pub execution: ExecutionConfig,
+
+    /// Output normalizers used by every test case that does not declare its
+    /// own with `//@ normalize:` directives.
+    #[serde(default)]
+    pub normalize: Vec<Normalizer>,
+
+    #[serde(default)]
+    pub comparison: ComparisonConfig,
+
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct CompilerConfig {
     pub path: PathBuf,
     pub args: Vec<String>,
+
+    /// Code appended to single-source test cases for this compiler.
+    #[serde(default)]
+    pub prelude: Option<String>,
+
+    /// Seconds each invocation may take, overriding `timeouts.compile`.
+    #[serde(default)]
+    pub compile_timeout: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ExecutionConfig {
+    /// Bytes of stdout and of stderr captured from each test binary.
+    pub max_output: usize,
+
+    /// Limits for every test binary, test cases can override each of them
+    /// with `//@ limit:` directives.
+    pub limits: ResourceLimits,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct ComparisonConfig {
+    /// Used by every test case without a `//@ stderr-mode:` directive.
+    pub stderr: StderrMode,
+
+    /// Name of the compiler every other one is compared against. Without
+    /// it every pair of `[[compilers]]` is compared.
+    pub reference: Option<String>,
+}
+
+/// Timeouts in seconds, test cases can override them with the
+/// `//@ compile-timeout:` and `//@ run-timeout:` directives.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    /// For each compiler invocation, a compiler that takes longer is killed
+    /// and reported as hanging.
+    pub compile: u64,
+    /// For each run of a test binary.
+    pub run: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DiagnosticsConfig {
+    /// Report warnings that only one compiler emits. Compiler output is
+    /// recorded in the report either way.
+    pub compare: bool,
+
+    /// Warnings compared between the compilers, replacing the built-in
+    /// `unused_variables` and `dead_code` mappings when given.
+    pub lints: Vec<LintMapping>,
+}
+
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        Self {
+            compare: false,
+            lints: LintMapping::defaults(),
+        }
+    }
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            compile: 60,
+            run: 5,
+        }
+    }
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            max_output: 1024 * 1024,
+            limits: ResourceLimits::default(),
+        }
+    }
+}
+
+impl CompilerConfig {
+    /// The compiler as a backend named after `dialect`, invoked as
+    /// `<path> <src> <args> <profile flags> -o <out>`.
+    pub fn backend(&self, dialect: CompilerKind) -> Backend {
+        let mut command = vec![
+            self.path.to_string_lossy().into_owned(),
+            SOURCE_PLACEHOLDER.to_string(),
+        ];
+        command.extend(self.args.iter().cloned());
+        command.extend([FLAGS_PLACEHOLDER, "-o", OUTPUT_PLACEHOLDER].map(String::from));
+        Backend {
+            name: dialect.to_string(),
+            command,
+            dialect,
+            opt_flags: Default::default(),
+            prelude: self.prelude.clone(),
+            compile_timeout: self.compile_timeout,
+        }
+    }
 }
 
 impl AppConfig {
+    /// The compilers test cases are built with, in config order. The classic
+    /// pair is listed as gccrs then rustc.
+    pub fn backends(&self) -> Vec<Backend> {
+        if !self.compilers.is_empty() {
+            return self.compilers.clone();
+        }
+        [
+            (CompilerKind::Gccrs, &self.gccrs),
+            (CompilerKind::Rustc, &self.rustc),
+        ]
+        .into_iter()
+        .filter_map(|(dialect, compiler)| compiler.as_ref().map(|c| c.backend(dialect)))
+        .collect()
+    }
+
+    /// Name of the compiler the others are compared against, if any. The
+    /// classic pair is compared against rustc.
+    pub fn reference(&self) -> Option<&str> {
+        match (&self.comparison.reference, self.compilers.is_empty()) {
+            (Some(reference), _) => Some(reference),
+            (None, true) => Some("rustc"),
+            (None, false) => None,
+        }
+    }
+
+    /// The profiles to build with. `no_opt` takes precedence over the
+    /// profiles from the config file, which in turn replace the default
+    /// optimization levels.
+    pub fn build_profiles(&self, no_opt: bool) -> Vec<BuildProfile> {
+        if no_opt {
+            vec![BuildProfile::from_optimization(Optimization::Zero)]
+        } else if self.profiles.is_empty() {
+            OPTIMIZATION_LEVELS
+                .iter()
+                .map(|level| BuildProfile::from_optimization(*level))
+                .collect()
+        } else {
+            self.profiles.clone()
+        }
+    }
+
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
         let path = path.as_ref();
         let content = fs::read_to_string(path)?;
@@ -37,18 +216,196 @@ impl AppConfig {
     }
 
     fn validate(&self) -> Result<(), ConfigError> {
-        if !self.rustc.path.exists() {
+        if self.compilers.is_empty() {
+            self.validate_pair()?;
+        } else {
+            self.validate_compilers()?;
+        }
+
+        let mut names = HashSet::new();
+        let mut stems = HashMap::new();
+        for profile in &self.profiles {
+            if profile.name.is_empty() {
+                return Err(ConfigError::Validation(
+                    "Build profile names must not be empty".to_string(),
+                ));
+            }
+            if !names.insert(&profile.name) {
+                return Err(ConfigError::Validation(format!(
+                    "Duplicate build profile: {}",
+                    profile.name
+                )));
+            }
+            // Binaries are named after the stem, so two profiles sharing one
+            // would overwrite each other's builds.
+            if let Some(other) = stems.insert(profile.file_stem(), &profile.name) {
+                return Err(ConfigError::Validation(format!(
+                    "Build profiles '{}' and '{}' have the same file name '{}'",
+                    other,
+                    profile.name,
+                    profile.file_stem()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_pair(&self) -> Result<(), ConfigError> {
+        let (Some(rustc), Some(gccrs)) = (&self.rustc, &self.gccrs) else {
+            return Err(ConfigError::Validation(
+                "Either [rustc] and [gccrs] or at least two [[compilers]] are required".to_string(),
+            ));
+        };
+        if !rustc.path.exists() {
             return Err(ConfigError::Validation(format!(
                 "Rustc path does not exist: {}",
-                self.rustc.path.display()
+                rustc.path.display()
             )));
         }
-        if !self.gccrs.path.exists() {
+        if !gccrs.path.exists() {
             return Err(ConfigError::Validation(format!(
                 "GCCRS path does not exist: {}",
-                self.gccrs.path.display()
+                gccrs.path.display()
             )));
         }
+        if let Some(reference) = &self.comparison.reference {
+            if reference != "rustc" && reference != "gccrs" {
+                return Err(ConfigError::Validation(format!(
+                    "Unknown reference compiler: {}",
+                    reference
+                )));
+            }
+        }
         Ok(())
     }
+
+    fn validate_compilers(&self) -> Result<(), ConfigError> {
+        if self.compilers.len() < 2 {
+            return Err(ConfigError::Validation(
+                "At least two [[compilers]] are needed to compare".to_string(),
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for compiler in &self.compilers {
+            // Names end up in file names, single-source variants are even
+            // compiled as crates named after them.
+            if compiler.name.is_empty()
+                || !compiler
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(ConfigError::Validation(format!(
+                    "Compiler names may only contain letters, digits, '-' and '_': '{}'",
+                    compiler.name
+                )));
+            }
+            if !names.insert(&compiler.name) {
+                return Err(ConfigError::Validation(format!(
+                    "Duplicate compiler: {}",
+                    compiler.name
+                )));
+            }
+            for placeholder in [SOURCE_PLACEHOLDER, OUTPUT_PLACEHOLDER] {
+                if !compiler.command.iter().any(|arg| arg.contains(placeholder)) {
+                    return Err(ConfigError::Validation(format!(
+                        "The command of compiler '{}' lacks {}",
+                        compiler.name, placeholder
+                    )));
+                }
+            }
+            let program = Path::new(compiler.program());
+            if program.components().count() > 1 && !program.exists() {
+                return Err(ConfigError::Validation(format!(
+                    "Path of compiler '{}' does not exist: {}",
+                    compiler.name,
+                    program.display()
+                )));
+            }
+        }
+
+        if let Some(reference) = &self.comparison.reference {
+            if !names.contains(reference) {
+                return Err(ConfigError::Validation(format!(
+                    "Unknown reference compiler: {}",
+                    reference
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(profiles: &str) -> Result<AppConfig, ConfigError> {
+        let config: AppConfig = toml::from_str(&format!(
+            r#"
+            [[compilers]]
+            name = "rustc"
+            command = ["rustc", "{{src}}", "-o", "{{out}}"]
+            dialect = "rustc"
+
+            [[compilers]]
+            name = "gccrs"
+            command = ["gccrs", "{{src}}", "-o", "{{out}}"]
+            dialect = "gccrs"
+
+            {}
+            "#,
+            profiles
+        ))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validation_error(profiles: &str) -> String {
+        match parse(profiles) {
+            Err(ConfigError::Validation(message)) => message,
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_profiles_with_distinct_file_names() {
+        let config = parse(
+            r#"
+            [[profiles]]
+            name = "O2 lto"
+            [[profiles]]
+            name = "O2-lto"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.profiles.len(), 2);
+    }
+
+    #[test]
+    fn rejects_profiles_sharing_a_file_name() {
+        assert_eq!(
+            validation_error(
+                r#"
+                [[profiles]]
+                name = "O2 lto"
+                [[profiles]]
+                name = "O2_lto"
+                "#
+            ),
+            "Build profiles 'O2 lto' and 'O2_lto' have the same file name 'O2_lto'"
+        );
+        assert_eq!(
+            validation_error(
+                r#"
+                [[profiles]]
+                name = "O2"
+                [[profiles]]
+                name = "O2"
+                "#
+            ),
+            "Duplicate build profile: O2"
+        );
+    }
 }
@@ -0,0 +1,348 @@
+mod rng;
+
+use crate::reporting::Report;
+use crate::session::Session;
+use crate::testsuite::{TestCase, TestSuiteError};
+use crate::utils;
+use log::{info, warn};
+use rng::Rng;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+const MAX_EXPR_DEPTH: usize = 3;
+const MAX_BLOCK_DEPTH: usize = 2;
+const MAX_LOOP_ITERATIONS: usize = 8;
+
+/// Integer operations whose operands are masked so that they never overflow,
+/// divide by zero or shift out of range. The programs are therefore free of
+/// undefined behavior and panics, and every compiler has to agree on them.
+const BINARY_OPS: [&str; 10] = [
+    "((A & 0x7fffffff) + (B & 0x7fffffff))",
+    "((A | 0x80000000) - (B & 0x7fffffff))",
+    "((A & 0xffff) * (B & 0xffff))",
+    "(A / ((B & 0xff) | 1))",
+    "(A % ((B & 0xff) | 1))",
+    "(A << (B & 31))",
+    "(A >> (B & 31))",
+    "(A & B)",
+    "(A | B)",
+    "(A ^ B)",
+];
+
+const COMPARISONS: [&str; 6] = ["<", "<=", ">", ">=", "==", "!="];
+
+#[derive(Debug, Error)]
+pub enum FuzzError {
+    #[error("I/O error for '{}': {error}", file.display())]
+    Io {
+        file: PathBuf,
+        error: std::io::Error,
+    },
+
+    #[error(transparent)]
+    TestSuite(#[from] TestSuiteError),
+}
+
+/// Generates `count` programs from consecutive seeds and runs each through
+/// the usual compile-and-compare pipeline. Programs that show differences are
+/// copied to `save_dir`, named after their seed so they can be regenerated.
+/// The generated sources are removed afterwards, whether fuzzing succeeded
+/// or not.
+pub fn fuzz(
+    session: &Session,
+    count: u64,
+    seed: u64,
+    save_dir: &Path,
+    jobs: usize,
+) -> Result<Report, FuzzError> {
+    info!("Fuzzing [{}] programs starting from seed {}", count, seed);
+    let scratch = session.workspace.root().join(".fuzz");
+    fs::create_dir_all(&scratch).map_err(|error| FuzzError::Io {
+        file: scratch.clone(),
+        error,
+    })?;
+
+    let report = fuzz_in(session, &scratch, count, seed, save_dir, jobs);
+    if let Err(e) = fs::remove_dir_all(&scratch) {
+        warn!("Could not remove '{}': {}", scratch.display(), e);
+    }
+    report
+}
+
+fn fuzz_in(
+    session: &Session,
+    scratch: &Path,
+    count: u64,
+    seed: u64,
+    save_dir: &Path,
+    jobs: usize,
+) -> Result<Report, FuzzError> {
+    let mut report = Report::new();
+    let seeds: Vec<u64> = (0..count).map(|index| seed.wrapping_add(index)).collect();
+    let results = utils::parallel_map(&seeds, jobs, |seed| {
+        let name = format!("fuzz_{:016x}", seed);
+        let source = scratch.join(format!("{}.rs", name));
+        fs::write(&source, generate(*seed)).map_err(|error| FuzzError::Io {
+            file: source.clone(),
+            error,
+        })?;
+        let case = TestCase::from_source(name, &source)?;
+
+        let mut report = Report::new();
+        session.compile_and_analyze_case(&case, &mut report);
+        if report.failed_tests > 0 {
+            let saved = save_dir.join(source.file_name().unwrap_or_default());
+            fs::create_dir_all(save_dir)
+                .and_then(|_| fs::copy(&source, &saved))
+                .map_err(|error| FuzzError::Io {
+                    file: saved.clone(),
+                    error,
+                })?;
+            warn!(
+                "Program from seed {} shows differences, saved to '{}'",
+                seed,
+                saved.display()
+            );
+        }
+        fs::remove_file(&source).ok();
+        Ok::<_, FuzzError>(report)
+    });
+
+    for result in results {
+        report.merge(result?);
+    }
+    Ok(report)
+}
+
+/// Generates a random, well-defined program in the single-source format,
+/// restricted to what gccrs supports: `u32` arithmetic, structs, `while`
+/// loops, `if` and calls to non-recursive functions. The program prints a
+/// checksum of its final state through `printf`.
+pub fn generate(seed: u64) -> String {
+    Generator {
+        rng: Rng::new(seed),
+        structs: Vec::new(),
+        functions: Vec::new(),
+        next_id: 0,
+    }
+    .program(seed)
+}
+
+struct Generator {
+    rng: Rng,
+    /// Field count of every generated struct.
+    structs: Vec<usize>,
+    /// Parameter count of every generated function.
+    functions: Vec<usize>,
+    next_id: usize,
+}
+
+#[derive(Clone, Default)]
+struct Scope {
+    readable: Vec<String>,
+    writable: Vec<String>,
+}
+
+impl Generator {
+    fn program(mut self, seed: u64) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "// Generated by the fuzz subcommand from seed {}",
+            seed
+        );
+        out.push_str(
+            "extern \"C\" {\n    fn printf(format: *const i8, ...);\n}\n\n\
+             fn mix(hash: u32, value: u32) -> u32 {\n    ((hash << 5) | (hash >> 27)) ^ value\n}\n\n",
+        );
+
+        for index in 0..self.rng.range(1, 2) {
+            let fields = self.rng.range(2, 4);
+            let _ = writeln!(out, "struct S{} {{", index);
+            for field in 0..fields {
+                let _ = writeln!(out, "    f{}: u32,", field);
+            }
+            out.push_str("}\n\n");
+            self.structs.push(fields);
+        }
+
+        for index in 0..self.rng.range(1, 4) {
+            let params = self.rng.range(1, 3);
+            let mut scope = Scope::default();
+            let signature: Vec<_> = (0..params)
+                .map(|param| {
+                    scope.readable.push(format!("p{}", param));
+                    format!("p{}: u32", param)
+                })
+                .collect();
+            let _ = writeln!(out, "fn f{}({}) -> u32 {{", index, signature.join(", "));
+            self.block(&mut out, &mut scope, 1, false);
+            let result = self.expr(&scope, 0);
+            let _ = writeln!(out, "    {}\n}}\n", result);
+            self.functions.push(params);
+        }
+
+        out.push_str("fn run() -> u32 {\n    let mut checksum: u32 = 0;\n");
+        let mut scope = Scope::default();
+        scope.readable.push("checksum".to_string());
+        for (index, fields) in self.structs.clone().into_iter().enumerate() {
+            let values: Vec<_> = (0..fields)
+                .map(|field| format!("f{}: {}", field, self.rng.next_u32()))
+                .collect();
+            let _ = writeln!(
+                out,
+                "    let mut s{} = S{} {{ {} }};",
+                index,
+                index,
+                values.join(", ")
+            );
+            for field in 0..fields {
+                scope.readable.push(format!("s{}.f{}", index, field));
+                scope.writable.push(format!("s{}.f{}", index, field));
+            }
+        }
+        self.block(&mut out, &mut scope, 1, true);
+        for value in scope.readable.iter().filter(|value| *value != "checksum") {
+            let _ = writeln!(out, "    checksum = mix(checksum, {});", value);
+        }
+        out.push_str("    checksum\n}\n\n");
+
+        out.push_str(
+            "fn main() -> i32 { //@ cfg(gccrs)\n\
+             fn main() { //@ cfg(rustc)\n\
+             \x20   let checksum = run();\n\
+             \x20   unsafe {\n\
+             \x20       printf(\"checksum = %u\\n\\0\" as *const str as *const i8, checksum);\n\
+             \x20   }\n\
+             \x20   0 //@ cfg(gccrs)\n\
+             }\n",
+        );
+        out
+    }
+
+    /// Emits a few statements at `depth` levels of nesting. Variables
+    /// declared inside stay in `scope` only for top-level blocks.
+    fn block(&mut self, out: &mut String, scope: &mut Scope, depth: usize, checksum: bool) {
+        let indent = "    ".repeat(depth);
+        for _ in 0..self.rng.range(2, 5) {
+            let nested = depth <= MAX_BLOCK_DEPTH;
+            match self.rng.range(0, 9) {
+                0..=2 => {
+                    let name = self.fresh("v");
+                    let value = self.expr(scope, 0);
+                    let _ = writeln!(out, "{}let mut {}: u32 = {};", indent, name, value);
+                    scope.readable.push(name.clone());
+                    scope.writable.push(name);
+                }
+                3..=4 if !scope.writable.is_empty() => {
+                    let target = self.rng.choose(&scope.writable).clone();
+                    let value = self.expr(scope, 0);
+                    let _ = writeln!(out, "{}{} = {};", indent, target, value);
+                }
+                5 if nested => {
+                    let counter = self.fresh("i");
+                    let iterations = self.rng.range(1, MAX_LOOP_ITERATIONS);
+                    let _ = writeln!(out, "{}let mut {}: u32 = 0;", indent, counter);
+                    let _ = writeln!(out, "{}while {} < {} {{", indent, counter, iterations);
+                    let mut inner = scope.clone();
+                    inner.readable.push(counter.clone());
+                    self.block(out, &mut inner, depth + 1, checksum);
+                    let _ = writeln!(out, "{}    {} = {} + 1;", indent, counter, counter);
+                    let _ = writeln!(out, "{}}}", indent);
+                }
+                6 if nested => {
+                    let condition = self.condition(scope);
+                    let _ = writeln!(out, "{}if {} {{", indent, condition);
+                    self.block(out, &mut scope.clone(), depth + 1, checksum);
+                    let _ = writeln!(out, "{}}} else {{", indent);
+                    self.block(out, &mut scope.clone(), depth + 1, checksum);
+                    let _ = writeln!(out, "{}}}", indent);
+                }
+                7 if checksum => {
+                    let value = self.expr(scope, 0);
+                    let _ = writeln!(out, "{}checksum = mix(checksum, {});", indent, value);
+                }
+                _ => {
+                    let name = self.fresh("v");
+                    let value = self.expr(scope, 0);
+                    let _ = writeln!(out, "{}let {}: u32 = {};", indent, name, value);
+                    scope.readable.push(name);
+                }
+            }
+        }
+    }
+
+    fn expr(&mut self, scope: &Scope, depth: usize) -> String {
+        if depth >= MAX_EXPR_DEPTH || self.rng.chance(30) {
+            return if scope.readable.is_empty() || self.rng.chance(30) {
+                format!("{}u32", self.rng.next_u32())
+            } else {
+                self.rng.choose(&scope.readable).clone()
+            };
+        }
+
+        match self.rng.range(0, 9) {
+            0 if !self.functions.is_empty() => {
+                let index = self.rng.range(0, self.functions.len() - 1);
+                let args: Vec<_> = (0..self.functions[index])
+                    .map(|_| self.expr(scope, depth + 1))
+                    .collect();
+                format!("f{}({})", index, args.join(", "))
+            }
+            1 => {
+                let condition = self.condition(scope);
+                let then = self.expr(scope, depth + 1);
+                let otherwise = self.expr(scope, depth + 1);
+                format!("(if {} {{ {} }} else {{ {} }})", condition, then, otherwise)
+            }
+            _ => {
+                let op = *self.rng.choose(&BINARY_OPS);
+                let lhs = self.expr(scope, depth + 1);
+                let rhs = self.expr(scope, depth + 1);
+                op.replace('A', &lhs).replace('B', &rhs)
+            }
+        }
+    }
+
+    fn condition(&mut self, scope: &Scope) -> String {
+        let lhs = self.expr(scope, MAX_EXPR_DEPTH - 1);
+        let rhs = self.expr(scope, MAX_EXPR_DEPTH - 1);
+        format!("{} {} {}", lhs, self.rng.choose(&COMPARISONS), rhs)
+    }
+
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_seed_always_generates_the_same_program() {
+        for seed in [0, 1, 0xdead_beef, u64::MAX] {
+            assert_eq!(generate(seed), generate(seed));
+        }
+    }
+
+    #[test]
+    fn different_seeds_generate_different_programs() {
+        let programs: Vec<_> = (0..16).map(|seed| body(&generate(seed))).collect();
+        for (index, program) in programs.iter().enumerate() {
+            assert!(
+                !programs[..index].contains(program),
+                "seed {} repeats an earlier program",
+                index
+            );
+        }
+    }
+
+    /// The program without its header comment, which names the seed.
+    fn body(program: &str) -> String {
+        program.split_once('\n').unwrap().1.to_string()
+    }
+}
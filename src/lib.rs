@@ -0,0 +1,29 @@
+//! Compiles Rust test programs with both rustc and gccrs, runs the resulting
+//! binaries and reports where their behavior differs.
+//!
+//! [`Session::compile_and_analyze_case`] drives one [`TestCase`] through the
+//! whole pipeline. The compiler drivers in [`compiler`] and the comparison in
+//! [`AnalysisContext`] can also be used on their own, or one step further
+//! down, binaries run with [`analysis::ExecutionContext`] and compared with
+//! [`Comparison`]. Whole suites run through [`Session::analyze_suite`], and
+//! the other subcommands of the CLI are [`reduce::reduce`], [`fuzz::fuzz`]
+//! and [`bisect::bisect`].
+
+pub mod analysis;
+pub mod bisect;
+pub mod compiler;
+pub mod config;
+pub mod fuzz;
+pub mod reduce;
+pub mod reporting;
+mod session;
+pub mod testsuite;
+pub mod utils;
+pub mod workspace;
+
+pub use analysis::{AnalysisContext, AnalysisError, AnalysisResult, Comparison, Diff};
+pub use compiler::{compile, BuildProfile, CompileError, CompilerKind};
+pub use config::AppConfig;
+pub use reporting::Report;
+pub use session::Session;
+pub use testsuite::{TestCase, TestSuite};
@@ -1,28 +1,27 @@
-mod analysis;
 mod cli;
-mod compiler;
-mod config;
-mod reporting;
-mod testsuite;
-mod utils;
-
-use crate::analysis::{AnalysisContext, AnalysisError};
-use crate::cli::{Cli, Mode};
-use crate::compiler::{compile_with, CompilerKind, Optimization, OPTIMIZATION_LEVELS};
-use crate::config::{AppConfig, ConfigError};
-use crate::reporting::{ErrorReporter, Report};
-use crate::testsuite::{TestCase, TestSuite, TestSuiteError};
+
+use crate::cli::{Cli, Mode, ReportFormat};
 use clap::Parser;
 use env_logger::Env;
-use log::{error, info};
+use log::{error, info, warn};
+use semantic_equivalence_validator::analysis::AnalysisError;
+use semantic_equivalence_validator::bisect::{bisect, BisectError, Builds, GitBuilds};
+use semantic_equivalence_validator::compiler::{find_backend, BuildProfile, OPTIMIZATION_LEVELS};
+use semantic_equivalence_validator::config::{AppConfig, ConfigError};
+use semantic_equivalence_validator::fuzz::{fuzz, FuzzError};
+use semantic_equivalence_validator::reduce::{reduce, ReduceError};
+use semantic_equivalence_validator::reporting::{
+    Baseline, BaselineError, JsonReport, Report, ReportComparison, SlowdownThreshold,
+};
+use semantic_equivalence_validator::testsuite::{TestSuite, TestSuiteError};
+use semantic_equivalence_validator::workspace::{BuildWorkspace, WorkspaceError};
+use semantic_equivalence_validator::Session;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
-use std::time::Duration;
-use std::time::Instant;
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
-const ANALYSIS_TIMEOUT: u64 = 5; // in secs
-
 #[derive(Debug, Error)]
 enum AppError {
     #[error(transparent)]
@@ -34,8 +33,11 @@ enum AppError {
     #[error(transparent)]
     Analysis(#[from] AnalysisError),
 
-    #[error("Compilation error for {compiler}:\n {message}")]
-    Compilation { compiler: String, message: String },
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+
+    #[error(transparent)]
+    Baseline(#[from] BaselineError),
 
     #[error("I/O error for '{file}': {error}")]
     Io {
@@ -43,8 +45,29 @@ enum AppError {
         error: std::io::Error,
     },
 
+    #[error("Invalid JSON report '{file}': {error}")]
+    ReportParse {
+        file: PathBuf,
+        error: serde_json::Error,
+    },
+
     #[error("Difference(s) found: {0}")]
     DifferenceFound(usize),
+
+    #[error("Regression(s) since the earlier report: {0}")]
+    Regression(usize),
+
+    #[error("Reduction failed: {0}")]
+    Reduce(#[from] ReduceError),
+
+    #[error(transparent)]
+    Fuzz(#[from] FuzzError),
+
+    #[error(transparent)]
+    Bisect(#[from] BisectError),
+
+    #[error("No compiler named '{0}' in the config file")]
+    UnknownCompiler(String),
 }
 
 fn init_logger() {
@@ -70,109 +93,260 @@ fn main() -> ExitCode {
 }
 
 fn run_app() -> Result<(), AppError> {
+    let args = Cli::parse();
+
+    // Comparing reports needs neither the compilers nor their config.
+    if let Mode::CompareReports {
+        old,
+        new,
+        slowdown,
+        min_slowdown_secs,
+        fail_on_slowdown,
+    } = &args.mode
+    {
+        let threshold = SlowdownThreshold {
+            factor: *slowdown,
+            min_secs: *min_slowdown_secs,
+        };
+        return run_compare_reports(old, new, &threshold, *fail_on_slowdown);
+    }
+
     let config = AppConfig::load("config/Compiler.toml")?;
     info!("Config file read successfully");
 
-    let args = Cli::parse();
+    let session = Session {
+        config: &config,
+        workspace: BuildWorkspace::new(&args.build_dir)?,
+        backends: config.backends(),
+        profiles: config.build_profiles(args.no_opt),
+        bless: args.bless,
+        self_diff: false,
+    };
+    let output = ReportOutput {
+        format: args.report_format,
+        file: args.report_file,
+        baseline: args.baseline,
+        update_baseline: args.update_baseline,
+    };
+
     match args.mode {
-        Mode::File { rustc, gccrs } => run_file(&rustc, &gccrs, &config, args.no_opt),
-        Mode::Dir { path } => run_directory(&path, &config, args.no_opt),
+        Mode::File { rustc, gccrs } => run_file(&rustc, &gccrs, &session, &output),
+        Mode::Dir { path } => {
+            info!("Running on '{}' directory", path.display());
+            let testsuite = TestSuite::from_dir(&path)?;
+            run_suite(&testsuite, &session, args.jobs, &output)
+        }
+        Mode::Source { path } => {
+            info!(
+                "Running on single-source test cases in '{}'",
+                path.display()
+            );
+            let testsuite = TestSuite::from_sources(&path)?;
+            run_suite(&testsuite, &session, args.jobs, &output)
+        }
+        Mode::SelfDiff { path, compiler } => {
+            let backend = find_backend(&session.backends, compiler.as_deref())
+                .cloned()
+                .ok_or_else(|| {
+                    AppError::UnknownCompiler(compiler.unwrap_or_else(|| "gccrs".to_string()))
+                })?;
+            info!(
+                "Comparing the optimization levels of {} on '{}'",
+                backend.name,
+                path.display()
+            );
+            let session = Session {
+                backends: vec![backend],
+                profiles: OPTIMIZATION_LEVELS
+                    .iter()
+                    .map(|level| BuildProfile::from_optimization(*level))
+                    .collect(),
+                self_diff: true,
+                ..session
+            };
+            let testsuite = TestSuite::from_sources(&path)?;
+            run_suite(&testsuite, &session, args.jobs, &output)
+        }
+        Mode::Reduce {
+            source,
+            gccrs,
+            opt_level,
+            output,
+        } => {
+            let session = Session {
+                profiles: vec![BuildProfile::from_optimization(opt_level)],
+                bless: false,
+                ..session
+            };
+            let output = output.unwrap_or_else(|| args.build_dir.join("reduced"));
+            reduce(&session, &source, gccrs.as_deref(), &output)?;
+            Ok(())
+        }
+        Mode::Fuzz {
+            count,
+            seed,
+            save_dir,
+        } => {
+            let seed = seed.unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_nanos() as u64)
+                    .unwrap_or_default()
+            });
+            output.finish(fuzz(&session, count, seed, &save_dir, args.jobs)?)
+        }
+        Mode::Bisect {
+            source,
+            gccrs,
+            opt_level,
+            builds,
+            repo,
+            good,
+            bad,
+            build_command,
+            binary,
+        } => {
+            let builds = match (repo, good, bad, build_command, binary) {
+                (Some(repo), Some(good), Some(bad), Some(build_command), Some(binary)) => {
+                    Builds::Git(GitBuilds::new(&repo, &good, &bad, build_command, &binary)?)
+                }
+                _ => Builds::Binaries(builds),
+            };
+            let session = Session {
+                profiles: vec![BuildProfile::from_optimization(opt_level)],
+                bless: false,
+                ..session
+            };
+            bisect(&session, &source, gccrs.as_deref(), &builds)?;
+            Ok(())
+        }
+        Mode::CompareReports { .. } => unreachable!("handled before loading the config"),
     }
 }
 
-fn run_file(rustc: &Path, gccrs: &Path, config: &AppConfig, no_opt: bool) -> Result<(), AppError> {
+fn run_file(
+    rustc: &Path,
+    gccrs: &Path,
+    session: &Session,
+    output: &ReportOutput,
+) -> Result<(), AppError> {
     let testsuite = TestSuite::from_file(rustc, gccrs)?;
-    let timeout = Duration::from_secs(ANALYSIS_TIMEOUT);
 
     let mut report = Report::new();
-    compile_and_analyze_case(&testsuite.cases[0], config, timeout, &mut report, no_opt);
+    session.compile_and_analyze_case(&testsuite.cases[0], &mut report);
 
-    report.print_summary();
-    Ok(())
+    output.emit(&report)
 }
 
-fn run_directory(path: &Path, config: &AppConfig, no_opt: bool) -> Result<(), AppError> {
-    info!("Running on '{}' directory", path.display());
-    let testsuite = TestSuite::from_dir(path)?;
+fn run_suite(
+    testsuite: &TestSuite,
+    session: &Session,
+    jobs: usize,
+    output: &ReportOutput,
+) -> Result<(), AppError> {
     info!("Validating [{}] test cases", testsuite.size);
+    output.finish(session.analyze_suite(&testsuite.cases, jobs))
+}
 
-    let timeout = Duration::from_secs(ANALYSIS_TIMEOUT);
-
-    let mut report = Report::new();
-
-    for case in &testsuite.cases {
-        compile_and_analyze_case(case, config, timeout, &mut report, no_opt);
-    }
+fn run_compare_reports(
+    old: &Path,
+    new: &Path,
+    threshold: &SlowdownThreshold,
+    fail_on_slowdown: bool,
+) -> Result<(), AppError> {
+    let load = |path: &Path| -> Result<JsonReport, AppError> {
+        let content = fs::read_to_string(path).map_err(|error| AppError::Io {
+            file: path.to_path_buf(),
+            error,
+        })?;
+        serde_json::from_str(&content).map_err(|error| AppError::ReportParse {
+            file: path.to_path_buf(),
+            error,
+        })
+    };
 
-    report.print_summary();
+    let comparison = ReportComparison::new(&load(old)?, &load(new)?, threshold);
+    comparison.print();
 
-    if report.failed_tests > 0 {
-        Err(AppError::DifferenceFound(report.failed_tests))
+    let regressions = comparison.regressions(fail_on_slowdown);
+    if regressions > 0 {
+        Err(AppError::Regression(regressions))
     } else {
         Ok(())
     }
 }
 
-fn compile_and_analyze_case(
-    case: &TestCase,
-    config: &AppConfig,
-    timeout: Duration,
-    report: &mut Report,
-    no_opt: bool,
-) {
-    if let Err(e) = compile_with_compiler(
-        &config.rustc.path,
-        &case.rustc,
-        &config.rustc.args,
-        CompilerKind::Rustc,
-    ) {
-        report.add_error(ErrorReporter::Compilation {
-            message: e.to_string(),
-        });
-        return;
-    }
-
-    if let Err(e) = compile_with_compiler(
-        &config.gccrs.path,
-        &case.gccrs,
-        &config.gccrs.args,
-        CompilerKind::Gccrs,
-    ) {
-        report.add_error(ErrorReporter::Compilation {
-            message: e.to_string(),
-        });
-        return;
-    }
+struct ReportOutput {
+    format: ReportFormat,
+    file: Option<PathBuf>,
+    baseline: Option<PathBuf>,
+    update_baseline: bool,
+}
 
-    info!("Starting analysis for case '{}' ...", case.name);
+impl ReportOutput {
+    /// Emits the report of a run and turns its failures into the result of
+    /// the run. With a baseline only failures it does not list count, unless
+    /// the baseline is being rewritten from this run.
+    fn finish(&self, mut report: Report) -> Result<(), AppError> {
+        let failures = match &self.baseline {
+            Some(path) if self.update_baseline => {
+                Baseline::from_report(&report).save(path)?;
+                info!("Baseline written to '{}'", path.display());
+                0
+            }
+            Some(path) => {
+                report.apply_baseline(&Baseline::load(path)?);
+                report.failures()
+            }
+            None => report.failures(),
+        };
 
-    let optimization_levels = if no_opt {
-        vec![Optimization::Zero]
-    } else {
-        OPTIMIZATION_LEVELS.to_vec()
-    };
+        self.emit(&report)?;
 
-    for level in optimization_levels {
-        let level_str = level.as_str();
-        let gccrs_bin_name = format!("out/gccrs_{}.out", level_str);
-        let rustc_bin_name = format!("out/rustc_{}.out", level_str);
-        let gccrs_binary = Path::new(&gccrs_bin_name);
-        let rustc_binary = Path::new(&rustc_bin_name);
-
-        let testname = case.name.clone() + " with opt-level = " + level_str;
-        let context = AnalysisContext::new(testname.clone(), gccrs_binary, rustc_binary, timeout);
-        let start = Instant::now();
-        let result = context.analyze();
-        let duration = start.elapsed();
-        report.add_result(testname, result, duration);
+        if failures > 0 {
+            Err(AppError::DifferenceFound(failures))
+        } else {
+            Ok(())
+        }
     }
-}
 
-fn compile_with_compiler(
-    compiler_path: &Path,
-    input_file: &Path,
-    args: &[String],
-    kind: CompilerKind,
-) -> Result<(), String> {
-    compile_with(compiler_path, input_file, args, kind).map_err(|e| e.to_string())
+    /// Machine-readable reports go to `--report-file` next to the terminal
+    /// summary, or replace the summary on stdout when no file is given.
+    fn emit(&self, report: &Report) -> Result<(), AppError> {
+        let rendered = match self.format {
+            ReportFormat::Text => {
+                if let Some(file) = &self.file {
+                    warn!(
+                        "Ignoring report file '{}' for the text format",
+                        file.display()
+                    );
+                }
+                report.print_summary();
+                return Ok(());
+            }
+            ReportFormat::Json => {
+                serde_json::to_string_pretty(&report.to_json()).map_err(|e| AppError::Io {
+                    file: self
+                        .file
+                        .clone()
+                        .unwrap_or_else(|| PathBuf::from("<stdout>")),
+                    error: e.into(),
+                })?
+            }
+            ReportFormat::Junit => report.to_junit(),
+        };
+
+        match &self.file {
+            None => println!("{}", rendered),
+            Some(file) => {
+                report.print_summary();
+                fs::write(file, rendered).map_err(|error| AppError::Io {
+                    file: file.clone(),
+                    error,
+                })?;
+                info!("Report written to '{}'", file.display());
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,247 @@
+use crate::session::Session;
+use crate::testsuite::{TestCase, TestSuiteError};
+use crate::workspace::{BuildWorkspace, WorkspaceError};
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ReduceError {
+    #[error("I/O error for '{}': {error}", file.display())]
+    Io {
+        file: PathBuf,
+        error: std::io::Error,
+    },
+
+    #[error(transparent)]
+    TestSuite(#[from] TestSuiteError),
+
+    #[error(transparent)]
+    Workspace(#[from] WorkspaceError),
+
+    #[error("invalid file name '{0}'")]
+    InvalidName(String),
+
+    #[error("'{case}' does not show any differences at {profile}")]
+    NoDifferences { case: String, profile: String },
+}
+
+/// Shrinks a failing test case with delta debugging, keeping only candidates
+/// that still fail with the same kinds of differences. Pairs are reduced one
+/// file at a time until neither file shrinks any further. The result is
+/// written to `output_dir` with [`write_candidate`]. The candidates are
+/// removed afterwards, whether the reduction succeeded or not.
+pub fn reduce(
+    session: &Session,
+    source: &Path,
+    gccrs: Option<&Path>,
+    output_dir: &Path,
+) -> Result<(), ReduceError> {
+    let scratch = session.workspace.root().join(".reduce");
+    let result = reduce_in(session, &scratch, source, gccrs, output_dir);
+    if let Err(e) = fs::remove_dir_all(&scratch) {
+        warn!("Could not remove '{}': {}", scratch.display(), e);
+    }
+    result
+}
+
+fn reduce_in(
+    session: &Session,
+    scratch: &Path,
+    source: &Path,
+    gccrs: Option<&Path>,
+    output_dir: &Path,
+) -> Result<(), ReduceError> {
+    let name = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| ReduceError::InvalidName(source.display().to_string()))?
+        .to_string();
+    let builds = BuildWorkspace::new(&scratch.join("build"))?;
+
+    let mut rustc_lines = read_lines(source)?;
+    let mut gccrs_lines = gccrs.map(read_lines).transpose()?;
+
+    let original = write_candidate(scratch, &name, &rustc_lines, gccrs_lines.as_deref())?;
+    let kinds =
+        session
+            .diff_kinds(&original, &builds)
+            .ok_or_else(|| ReduceError::NoDifferences {
+                case: name.clone(),
+                profile: session.profiles[0].name.clone(),
+            })?;
+    info!("Reducing '{}' while it shows [{}]", name, kinds.join(", "));
+
+    let reproduces = |rustc: &[String], gccrs: Option<&[String]>| {
+        write_candidate(scratch, &name, rustc, gccrs)
+            .ok()
+            .and_then(|case| session.diff_kinds(&case, &builds))
+            .is_some_and(|candidate_kinds| candidate_kinds == kinds)
+    };
+
+    loop {
+        let before = (rustc_lines.len(), gccrs_lines.as_ref().map(Vec::len));
+        rustc_lines = ddmin(rustc_lines, |candidate| {
+            reproduces(candidate, gccrs_lines.as_deref())
+        });
+        if let Some(lines) = gccrs_lines.take() {
+            gccrs_lines = Some(ddmin(lines, |candidate| {
+                reproduces(&rustc_lines, Some(candidate))
+            }));
+        }
+        info!(
+            "Reduced '{}' to {} line(s){}",
+            name,
+            rustc_lines.len(),
+            gccrs_lines
+                .as_ref()
+                .map(|lines| format!(" for rustc and {} for gccrs", lines.len()))
+                .unwrap_or_default()
+        );
+        if before == (rustc_lines.len(), gccrs_lines.as_ref().map(Vec::len)) {
+            break;
+        }
+    }
+
+    write_candidate(output_dir, &name, &rustc_lines, gccrs_lines.as_deref())?;
+    info!("Reduced test case written to '{}'", output_dir.display());
+    Ok(())
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>, ReduceError> {
+    let content = fs::read_to_string(path).map_err(|error| ReduceError::Io {
+        file: path.to_path_buf(),
+        error,
+    })?;
+    Ok(content.lines().map(str::to_string).collect())
+}
+
+/// Writes a candidate in the layout the `source` and `dir` modes expect:
+/// `<dir>/<name>.rs`, or `<dir>/rustc/<name>.rs` and `<dir>/gccrs/<name>.rs`.
+pub fn write_candidate(
+    dir: &Path,
+    name: &str,
+    rustc: &[String],
+    gccrs: Option<&[String]>,
+) -> Result<TestCase, ReduceError> {
+    let write = |path: PathBuf, lines: &[String]| {
+        let parent = path.parent().unwrap_or(dir);
+        fs::create_dir_all(parent)
+            .and_then(|_| fs::write(&path, lines.join("\n") + "\n"))
+            .map(|_| path.clone())
+            .map_err(|error| ReduceError::Io { file: path, error })
+    };
+
+    let file_name = format!("{}.rs", name);
+    let case = match gccrs {
+        Some(gccrs) => {
+            let rustc = write(dir.join("rustc").join(&file_name), rustc)?;
+            let gccrs = write(dir.join("gccrs").join(&file_name), gccrs)?;
+            TestCase::from_pair(name.to_string(), rustc, gccrs)?
+        }
+        None => {
+            let source = write(dir.join(&file_name), rustc)?;
+            TestCase::from_source(name.to_string(), &source)?
+        }
+    };
+    Ok(case)
+}
+
+/// Delta debugging (ddmin) over the lines of a source file: repeatedly drops
+/// chunks of lines while `is_interesting` still holds for what is left, and
+/// returns the smallest set of lines found.
+pub fn ddmin<F>(mut lines: Vec<String>, mut is_interesting: F) -> Vec<String>
+where
+    F: FnMut(&[String]) -> bool,
+{
+    let mut granularity = 2;
+
+    while lines.len() >= 2 {
+        let chunk_size = lines.len().div_ceil(granularity);
+        let mut reduced = false;
+
+        for start in (0..lines.len()).step_by(chunk_size) {
+            let end = (start + chunk_size).min(lines.len());
+            let complement: Vec<String> = lines[..start]
+                .iter()
+                .chain(&lines[end..])
+                .cloned()
+                .collect();
+
+            if is_interesting(&complement) {
+                lines = complement;
+                granularity = (granularity - 1).max(2);
+                reduced = true;
+                break;
+            }
+        }
+
+        if !reduced {
+            if granularity >= lines.len() {
+                break;
+            }
+            granularity = (granularity * 2).min(lines.len());
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(count: usize) -> Vec<String> {
+        (0..count).map(|n| n.to_string()).collect()
+    }
+
+    fn contains_all(candidate: &[String], needed: &[&str]) -> bool {
+        needed
+            .iter()
+            .all(|line| candidate.iter().any(|l| l == line))
+    }
+
+    #[test]
+    fn keeps_only_the_needed_lines() {
+        let needed = ["3", "11", "12", "29"];
+        let reduced = ddmin(lines(30), |candidate| contains_all(candidate, &needed));
+        assert_eq!(reduced, needed);
+    }
+
+    #[test]
+    fn result_is_one_minimal() {
+        // Interesting while an even number of the first ten lines is left, so
+        // there are many local minima.
+        let interesting = |candidate: &[String]| {
+            let count = candidate.iter().filter(|l| l.len() == 1).count();
+            count > 0 && count % 2 == 0
+        };
+        let reduced = ddmin(lines(20), interesting);
+        assert!(interesting(&reduced));
+        for index in 0..reduced.len() {
+            let mut smaller = reduced.clone();
+            smaller.remove(index);
+            assert!(!interesting(&smaller), "{:?} is not 1-minimal", reduced);
+        }
+    }
+
+    #[test]
+    fn keeps_everything_when_nothing_can_go() {
+        assert_eq!(ddmin(lines(5), |candidate| candidate.len() == 5), lines(5));
+    }
+
+    #[test]
+    fn short_inputs_are_left_alone() {
+        let mut calls = 0;
+        assert_eq!(
+            ddmin(lines(1), |_| {
+                calls += 1;
+                true
+            }),
+            lines(1)
+        );
+        assert_eq!(ddmin(Vec::new(), |_| true), Vec::<String>::new());
+        assert_eq!(calls, 0);
+    }
+}
@@ -0,0 +1,264 @@
+use super::report::{AnalysisReport, Report};
+use crate::analysis::{AnalysisError, Diff};
+use serde_derive::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Kind recorded for results that could not be compared at all.
+const EXECUTION_ERROR_KIND: &str = "execution-error";
+
+#[derive(Debug, Error)]
+pub enum BaselineError {
+    #[error("Failed to read baseline '{}': {error}", path.display())]
+    Read {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+
+    #[error("Failed to parse baseline '{}': {error}", path.display())]
+    Parse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+
+    #[error("Failed to serialize baseline: {0}")]
+    Serialize(#[from] toml::ser::Error),
+
+    #[error("Failed to write baseline '{}': {error}", path.display())]
+    Write {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+}
+
+/// Differences that are known and expected, one entry per test case, build
+/// profile and kind of difference.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    #[serde(default, rename = "known-failure")]
+    pub known_failures: Vec<KnownFailure>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct KnownFailure {
+    pub case: String,
+    pub profile: String,
+    /// A [`Diff::kind`], or `execution-error`.
+    pub kind: String,
+}
+
+/// How a result relates to the baseline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaselineStatus {
+    Pass,
+    /// Failed with nothing but differences listed in the baseline.
+    ExpectedFail,
+    /// Passed although the baseline lists differences for it.
+    UnexpectedPass,
+    /// Failed with at least one difference the baseline does not list.
+    NewFailure,
+}
+
+impl BaselineStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BaselineStatus::Pass => "pass",
+            BaselineStatus::ExpectedFail => "expected-fail",
+            BaselineStatus::UnexpectedPass => "unexpected-pass",
+            BaselineStatus::NewFailure => "new-failure",
+        }
+    }
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Self, BaselineError> {
+        let content = fs::read_to_string(path).map_err(|error| BaselineError::Read {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        toml::from_str(&content).map_err(|error| BaselineError::Parse {
+            path: path.to_path_buf(),
+            error,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), BaselineError> {
+        let content = toml::to_string(self)?;
+        fs::write(path, content).map_err(|error| BaselineError::Write {
+            path: path.to_path_buf(),
+            error,
+        })
+    }
+
+    /// Records every difference of every failing result in `report`.
+    pub fn from_report(report: &Report) -> Self {
+        let mut known_failures: Vec<_> = report
+            .analysis_reports
+            .iter()
+            .flat_map(|result| {
+                failure_kinds(&result.result)
+                    .into_iter()
+                    .map(|kind| KnownFailure {
+                        case: result.case.clone(),
+                        profile: result.profile.clone(),
+                        kind: kind.to_string(),
+                    })
+            })
+            .collect();
+        known_failures.sort();
+        known_failures.dedup();
+        Self { known_failures }
+    }
+
+    pub fn classify(&self, result: &AnalysisReport) -> BaselineStatus {
+        let known: Vec<_> = self
+            .known_failures
+            .iter()
+            .filter(|failure| failure.case == result.case && failure.profile == result.profile)
+            .map(|failure| failure.kind.as_str())
+            .collect();
+        let kinds = failure_kinds(&result.result);
+
+        match (kinds.is_empty(), known.is_empty()) {
+            (true, true) => BaselineStatus::Pass,
+            (true, false) => BaselineStatus::UnexpectedPass,
+            _ if kinds.iter().all(|kind| known.contains(kind)) => BaselineStatus::ExpectedFail,
+            _ => BaselineStatus::NewFailure,
+        }
+    }
+}
+
+fn failure_kinds(result: &Result<(), AnalysisError>) -> Vec<&'static str> {
+    match result {
+        Ok(()) => Vec::new(),
+        Err(AnalysisError::ComparisonFailed(comparison, _)) => {
+            let mut kinds: Vec<_> = comparison.differences.iter().map(Diff::kind).collect();
+            kinds.sort_unstable();
+            kinds.dedup();
+            kinds
+        }
+        Err(AnalysisError::Execution(_)) => vec![EXECUTION_ERROR_KIND],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{AnalysisResult, ExecutionError};
+    use std::time::Duration;
+
+    fn differences(differences: Vec<Diff>) -> Result<(), AnalysisError> {
+        Err(AnalysisError::ComparisonFailed(
+            AnalysisResult {
+                differences,
+                compilers: ["gccrs".to_string(), "rustc".to_string()],
+            },
+            "case".to_string(),
+        ))
+    }
+
+    fn stdout() -> Diff {
+        Diff::Stdout("1\n".to_string(), "2\n".to_string())
+    }
+
+    fn report(results: Vec<(&str, Result<(), AnalysisError>)>) -> Report {
+        let mut report = Report::new();
+        for (case, result) in results {
+            report.add_result(case, "debug", ["debug"; 2], result, Duration::ZERO);
+        }
+        report
+    }
+
+    fn known(case: &str, kind: &str) -> KnownFailure {
+        KnownFailure {
+            case: case.to_string(),
+            profile: "debug".to_string(),
+            kind: kind.to_string(),
+        }
+    }
+
+    fn classify(baseline: &Baseline, result: Result<(), AnalysisError>) -> BaselineStatus {
+        baseline.classify(&report(vec![("a", result)]).analysis_reports[0])
+    }
+
+    #[test]
+    fn records_each_kind_once() {
+        let report = report(vec![
+            (
+                "b",
+                differences(vec![stdout(), Diff::Timeout(true, false), stdout()]),
+            ),
+            ("a", Ok(())),
+            (
+                "a",
+                Err(ExecutionError::Failed("no binary".to_string()).into()),
+            ),
+        ]);
+        assert_eq!(
+            Baseline::from_report(&report).known_failures,
+            [
+                known("a", EXECUTION_ERROR_KIND),
+                known("b", "stdout"),
+                known("b", "timeout"),
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_against_known_failures() {
+        let baseline = Baseline {
+            known_failures: vec![known("a", "stdout"), known("a", "timeout")],
+        };
+        assert_eq!(
+            classify(&baseline, differences(vec![stdout()])),
+            BaselineStatus::ExpectedFail
+        );
+        assert_eq!(
+            classify(
+                &baseline,
+                differences(vec![Diff::Stderr(String::new(), "x".to_string())])
+            ),
+            BaselineStatus::NewFailure
+        );
+        assert_eq!(
+            classify(
+                &baseline,
+                differences(vec![stdout(), Diff::Stderr(String::new(), "x".to_string())])
+            ),
+            BaselineStatus::NewFailure
+        );
+        assert_eq!(classify(&baseline, Ok(())), BaselineStatus::UnexpectedPass);
+        assert_eq!(classify(&Baseline::default(), Ok(())), BaselineStatus::Pass);
+        assert_eq!(
+            classify(&Baseline::default(), differences(vec![stdout()])),
+            BaselineStatus::NewFailure
+        );
+    }
+
+    #[test]
+    fn matches_case_and_profile() {
+        let mut other_profile = known("a", "stdout");
+        other_profile.profile = "release".to_string();
+        let baseline = Baseline {
+            known_failures: vec![known("b", "stdout"), other_profile],
+        };
+        assert_eq!(
+            classify(&baseline, differences(vec![stdout()])),
+            BaselineStatus::NewFailure
+        );
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let baseline = Baseline {
+            known_failures: vec![known("a", "stdout")],
+        };
+        let parsed: Baseline = toml::from_str(&toml::to_string(&baseline).unwrap()).unwrap();
+        assert_eq!(parsed.known_failures, baseline.known_failures);
+        assert!(toml::from_str::<Baseline>("")
+            .unwrap()
+            .known_failures
+            .is_empty());
+    }
+}
@@ -0,0 +1,347 @@
+use super::json::{JsonError, JsonReport, JsonResult};
+use colored::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Thresholds above which a result counts as having slowed down.
+pub struct SlowdownThreshold {
+    /// New duration over old duration.
+    pub factor: f64,
+    /// Changes smaller than this are noise, whatever the factor.
+    pub min_secs: f64,
+}
+
+/// What changed between two runs, per build profile.
+#[derive(Default)]
+pub struct ReportComparison {
+    pub profiles: BTreeMap<String, ProfileChanges>,
+    /// Compile errors, compiler crashes and timeouts of the new run that the
+    /// old one did not have, per test case.
+    pub new_errors: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Default)]
+pub struct ProfileChanges {
+    pub newly_failing: Vec<String>,
+    pub newly_passing: Vec<String>,
+    pub still_failing: Vec<String>,
+    /// Case with its old and new duration in seconds.
+    pub slowdowns: Vec<(String, f64, f64)>,
+}
+
+impl ReportComparison {
+    /// Pairs up results by test case and profile. Results only present in
+    /// `new` count as passing before, those only in `old` as failing now: the
+    /// case no longer compiles, or was dropped from the run.
+    pub fn new(old: &JsonReport, new: &JsonReport, threshold: &SlowdownThreshold) -> Self {
+        let old_results: BTreeMap<_, _> = old
+            .results
+            .iter()
+            .map(|result| ((result.case.as_str(), result.profile.as_str()), result))
+            .collect();
+
+        let mut comparison = Self::default();
+        for result in &new.results {
+            let previous = old_results.get(&(result.case.as_str(), result.profile.as_str()));
+            let changes = comparison
+                .profiles
+                .entry(result.profile.clone())
+                .or_default();
+            let case = result.case.clone();
+
+            match (previous.is_some_and(|p| failed(p)), failed(result)) {
+                (false, true) => changes.newly_failing.push(case),
+                (true, false) => changes.newly_passing.push(case),
+                (true, true) => changes.still_failing.push(case),
+                (false, false) => {}
+            }
+
+            if let Some(previous) = previous {
+                let (before, after) = (previous.duration_secs, result.duration_secs);
+                if after - before >= threshold.min_secs && after > before * threshold.factor {
+                    changes.slowdowns.push((result.case.clone(), before, after));
+                }
+            }
+        }
+
+        let new_results: BTreeSet<_> = new
+            .results
+            .iter()
+            .map(|result| (result.case.as_str(), result.profile.as_str()))
+            .collect();
+        for ((case, profile), _) in old_results {
+            if !new_results.contains(&(case, profile)) {
+                comparison
+                    .profiles
+                    .entry(profile.to_string())
+                    .or_default()
+                    .newly_failing
+                    .push(format!("{} (no result)", case));
+            }
+        }
+
+        let old_errors: BTreeSet<_> = old.errors.iter().filter_map(build_error).collect();
+        for (case, error) in new.errors.iter().filter_map(build_error) {
+            if !old_errors.contains(&(case, error.clone())) {
+                comparison
+                    .new_errors
+                    .entry(case.to_string())
+                    .or_default()
+                    .push(error);
+            }
+        }
+        comparison
+    }
+
+    pub fn newly_failing(&self) -> usize {
+        self.profiles.values().map(|c| c.newly_failing.len()).sum()
+    }
+
+    pub fn new_errors(&self) -> usize {
+        self.new_errors.values().map(Vec::len).sum()
+    }
+
+    pub fn slowdowns(&self) -> usize {
+        self.profiles.values().map(|c| c.slowdowns.len()).sum()
+    }
+
+    /// Changes that make the comparison fail: newly failing results and new
+    /// build errors, and slowdowns if `include_slowdowns` is set.
+    pub fn regressions(&self, include_slowdowns: bool) -> usize {
+        let slowdowns = if include_slowdowns {
+            self.slowdowns()
+        } else {
+            0
+        };
+        self.newly_failing() + self.new_errors() + slowdowns
+    }
+
+    pub fn print(&self) {
+        for (profile, changes) in &self.profiles {
+            println!("{}", format!("=== {} ===", profile).bold());
+            print_cases("Newly failing", &changes.newly_failing, Color::Red);
+            print_cases("Newly passing", &changes.newly_passing, Color::Green);
+            print_cases("Still failing", &changes.still_failing, Color::Yellow);
+
+            println!(
+                "{} ({}):",
+                "Duration regressions".bold(),
+                changes.slowdowns.len()
+            );
+            for (case, before, after) in &changes.slowdowns {
+                println!(
+                    "  {}: {:.3}s -> {}",
+                    case,
+                    before,
+                    format!("{:.3}s", after).yellow()
+                );
+            }
+        }
+
+        if !self.new_errors.is_empty() {
+            println!("{}", "=== New build errors ===".bold());
+            for (case, errors) in &self.new_errors {
+                for error in errors {
+                    println!("  {}: {}", case, error.red());
+                }
+            }
+        }
+
+        println!("{}", "-".repeat(40).dimmed());
+        println!(
+            "{} {}",
+            "Newly failing:".bold(),
+            self.newly_failing().to_string().red()
+        );
+        println!(
+            "{} {}",
+            "Newly passing:".bold(),
+            self.profiles
+                .values()
+                .map(|c| c.newly_passing.len())
+                .sum::<usize>()
+                .to_string()
+                .green()
+        );
+        println!(
+            "{} {}",
+            "New build errors:".bold(),
+            self.new_errors().to_string().red()
+        );
+        println!(
+            "{} {}",
+            "Duration regressions:".bold(),
+            self.slowdowns().to_string().yellow()
+        );
+    }
+}
+
+fn failed(result: &JsonResult) -> bool {
+    result.status != "passed"
+}
+
+/// The case and a description of an error that kept a case from being
+/// built, leaving out its message, which changes from run to run.
+fn build_error(error: &JsonError) -> Option<(&str, String)> {
+    if !matches!(
+        error.kind.as_str(),
+        "compilation" | "compiler-timeout" | "compiler-crash"
+    ) {
+        return None;
+    }
+    let description = match &error.compiler {
+        Some(compiler) => format!("{} ({})", error.kind, compiler),
+        None => error.kind.clone(),
+    };
+    Some((error.case.as_deref()?, description))
+}
+
+fn print_cases(title: &str, cases: &[String], color: Color) {
+    println!("{} ({}):", title.bold(), cases.len());
+    for case in cases {
+        println!("  {}", case.color(color));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reporting::json::JsonSummary;
+
+    const THRESHOLD: SlowdownThreshold = SlowdownThreshold {
+        factor: 1.5,
+        min_secs: 0.5,
+    };
+
+    fn result(case: &str, status: &str, duration_secs: f64) -> JsonResult {
+        JsonResult {
+            case: case.to_string(),
+            profile: "O0".to_string(),
+            status: status.to_string(),
+            duration_secs,
+            diffs: Vec::new(),
+            error: None,
+            compilers: Vec::new(),
+            baseline: None,
+        }
+    }
+
+    fn error(kind: &str, case: &str, compiler: &str) -> JsonError {
+        JsonError {
+            kind: kind.to_string(),
+            case: Some(case.to_string()),
+            compiler: Some(compiler.to_string()),
+            message: format!("{} failed", compiler),
+        }
+    }
+
+    fn report(results: Vec<JsonResult>, errors: Vec<JsonError>) -> JsonReport {
+        JsonReport {
+            summary: JsonSummary {
+                total: results.len(),
+                passed: 0,
+                failed: 0,
+                duration_secs: 0.0,
+            },
+            errors,
+            results,
+            compilations: Vec::new(),
+            diagnostic_findings: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn classifies_results_by_case_and_profile() {
+        let old = report(
+            vec![
+                result("regressed", "passed", 1.0),
+                result("fixed", "failed", 1.0),
+                result("broken", "failed", 1.0),
+                result("stable", "passed", 1.0),
+                result("dropped", "passed", 1.0),
+            ],
+            Vec::new(),
+        );
+        let new = report(
+            vec![
+                result("regressed", "failed", 1.0),
+                result("fixed", "passed", 1.0),
+                result("broken", "error", 1.0),
+                result("stable", "passed", 1.0),
+                result("added", "passed", 1.0),
+                result("added-failing", "failed", 1.0),
+            ],
+            Vec::new(),
+        );
+
+        let comparison = ReportComparison::new(&old, &new, &THRESHOLD);
+        let changes = &comparison.profiles["O0"];
+        assert_eq!(
+            changes.newly_failing,
+            ["regressed", "added-failing", "dropped (no result)"]
+        );
+        assert_eq!(changes.newly_passing, ["fixed"]);
+        assert_eq!(changes.still_failing, ["broken"]);
+        assert!(changes.slowdowns.is_empty());
+        assert_eq!(comparison.regressions(true), 3);
+    }
+
+    #[test]
+    fn reports_only_new_build_errors() {
+        let old = report(
+            Vec::new(),
+            vec![
+                error("compilation", "a", "gccrs"),
+                error("compiler-crash", "b", "gccrs"),
+            ],
+        );
+        let new = report(
+            Vec::new(),
+            vec![
+                error("compilation", "a", "gccrs"),
+                error("compiler-timeout", "b", "gccrs"),
+                error("compilation", "c", "rustc"),
+                error("execution", "d", "gccrs"),
+            ],
+        );
+
+        let comparison = ReportComparison::new(&old, &new, &THRESHOLD);
+        assert_eq!(
+            comparison.new_errors.into_iter().collect::<Vec<_>>(),
+            [
+                (
+                    "b".to_string(),
+                    vec!["compiler-timeout (gccrs)".to_string()]
+                ),
+                ("c".to_string(), vec!["compilation (rustc)".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn slowdowns_only_fail_the_comparison_when_asked_to() {
+        let old = report(
+            vec![
+                result("slower", "passed", 1.0),
+                result("noise", "passed", 0.1),
+                result("steady", "passed", 10.0),
+            ],
+            Vec::new(),
+        );
+        let new = report(
+            vec![
+                result("slower", "passed", 2.0),
+                result("noise", "passed", 0.4),
+                result("steady", "passed", 12.0),
+            ],
+            Vec::new(),
+        );
+
+        let comparison = ReportComparison::new(&old, &new, &THRESHOLD);
+        assert_eq!(
+            comparison.profiles["O0"].slowdowns,
+            [("slower".to_string(), 1.0, 2.0)]
+        );
+        assert_eq!(comparison.regressions(false), 0);
+        assert_eq!(comparison.regressions(true), 1);
+    }
+}
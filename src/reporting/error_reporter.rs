@@ -1,18 +1,77 @@
 use crate::analysis::AnalysisError;
+use crate::workspace::WorkspaceError;
 use std::fmt;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub enum ErrorReporter {
     Analysis(AnalysisError),
-    Compilation { message: String },
+    Compilation {
+        case: String,
+        compiler: String,
+        message: String,
+    },
+    /// The compiler was killed after running for longer than `timeout`.
+    CompilerTimeout {
+        case: String,
+        compiler: String,
+        profile: String,
+        timeout: Duration,
+    },
+    /// The compiler hit an internal error or was killed by a signal.
+    CompilerCrash {
+        case: String,
+        compiler: String,
+        profile: String,
+        reason: String,
+        message: String,
+    },
+    Workspace(WorkspaceError),
+    Golden {
+        case: String,
+        message: String,
+    },
 }
 
 impl fmt::Display for ErrorReporter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ErrorReporter::Analysis(e) => write!(f, "Analysis error: {}", e),
-            ErrorReporter::Compilation { message } => {
-                write!(f, "{}", message)
+            ErrorReporter::Compilation {
+                case,
+                compiler,
+                message,
+            } => {
+                write!(
+                    f,
+                    "Compilation error for {} in '{}':\n {}",
+                    compiler, case, message
+                )
+            }
+            ErrorReporter::CompilerTimeout {
+                case,
+                compiler,
+                profile,
+                timeout,
+            } => write!(
+                f,
+                "{} hung on '{}' with {}, killed after {:?}",
+                compiler, case, profile, timeout
+            ),
+            ErrorReporter::CompilerCrash {
+                case,
+                compiler,
+                profile,
+                reason,
+                message,
+            } => write!(
+                f,
+                "{} crashed on '{}' with {}, {}:\n {}",
+                compiler, case, profile, reason, message
+            ),
+            ErrorReporter::Workspace(e) => write!(f, "Workspace error: {}", e),
+            ErrorReporter::Golden { case, message } => {
+                write!(f, "Failed to bless '{}': {}", case, message)
             }
         }
     }
@@ -23,3 +82,9 @@ impl From<AnalysisError> for ErrorReporter {
         ErrorReporter::Analysis(error)
     }
 }
+
+impl From<WorkspaceError> for ErrorReporter {
+    fn from(error: WorkspaceError) -> Self {
+        ErrorReporter::Workspace(error)
+    }
+}
@@ -0,0 +1,298 @@
+use super::error_reporter::ErrorReporter;
+use super::report::{AnalysisReport, CompilerInvocation, DiagnosticFinding, Report};
+use crate::analysis::{AnalysisError, Diff, Termination, TerminationKind, TokenMismatch};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Serializable form of a [`Report`].
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonReport {
+    pub summary: JsonSummary,
+    pub errors: Vec<JsonError>,
+    pub results: Vec<JsonResult>,
+    /// Output of every compiler invocation, warnings included.
+    #[serde(default)]
+    pub compilations: Vec<JsonCompilation>,
+    #[serde(default)]
+    pub diagnostic_findings: Vec<JsonDiagnosticFinding>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonSummary {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub duration_secs: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonError {
+    pub kind: String,
+    pub case: Option<String>,
+    pub compiler: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonResult {
+    pub case: String,
+    pub profile: String,
+    pub status: String,
+    pub duration_secs: f64,
+    pub diffs: Vec<JsonDiff>,
+    pub error: Option<String>,
+    /// The compilers compared, whose values the `left` and `right` keys of
+    /// each diff hold, in that order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compilers: Vec<String>,
+    /// Classification against the baseline, when one was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonDiff {
+    pub kind: String,
+    pub left: Value,
+    pub right: Value,
+    /// Golden value for differences against the expected output, or the
+    /// errors a compiler did not report for an error mismatch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonCompilation {
+    pub case: String,
+    pub profile: String,
+    pub compiler: String,
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JsonDiagnosticFinding {
+    pub case: String,
+    pub profile: String,
+    pub lint: String,
+    pub line: Option<usize>,
+    /// The only compiler that reported the warning.
+    pub compiler: String,
+    pub message: String,
+}
+
+impl Report {
+    pub fn to_json(&self) -> JsonReport {
+        JsonReport {
+            summary: JsonSummary {
+                total: self.total_tests,
+                passed: self.passed_tests,
+                failed: self.failed_tests,
+                duration_secs: self.start_time.elapsed().as_secs_f64(),
+            },
+            errors: self.errors.iter().map(JsonError::from).collect(),
+            results: self.analysis_reports.iter().map(JsonResult::from).collect(),
+            compilations: self
+                .compilations
+                .iter()
+                .map(JsonCompilation::from)
+                .collect(),
+            diagnostic_findings: self
+                .diagnostic_findings
+                .iter()
+                .map(JsonDiagnosticFinding::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&ErrorReporter> for JsonError {
+    fn from(error: &ErrorReporter) -> Self {
+        match error {
+            ErrorReporter::Compilation {
+                case,
+                compiler,
+                message,
+            } => JsonError {
+                kind: "compilation".to_string(),
+                case: Some(case.clone()),
+                compiler: Some(compiler.clone()),
+                message: message.clone(),
+            },
+            ErrorReporter::CompilerTimeout { case, compiler, .. } => JsonError {
+                kind: "compiler-timeout".to_string(),
+                case: Some(case.clone()),
+                compiler: Some(compiler.clone()),
+                message: error.to_string(),
+            },
+            ErrorReporter::CompilerCrash { case, compiler, .. } => JsonError {
+                kind: "compiler-crash".to_string(),
+                case: Some(case.clone()),
+                compiler: Some(compiler.clone()),
+                message: error.to_string(),
+            },
+            ErrorReporter::Analysis(e) => JsonError {
+                kind: "analysis".to_string(),
+                case: None,
+                compiler: None,
+                message: e.to_string(),
+            },
+            ErrorReporter::Golden { case, message } => JsonError {
+                kind: "golden".to_string(),
+                case: Some(case.clone()),
+                compiler: None,
+                message: message.clone(),
+            },
+            ErrorReporter::Workspace(e) => JsonError {
+                kind: "workspace".to_string(),
+                case: None,
+                compiler: None,
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+impl From<&CompilerInvocation> for JsonCompilation {
+    fn from(compilation: &CompilerInvocation) -> Self {
+        JsonCompilation {
+            case: compilation.case.clone(),
+            profile: compilation.profile.clone(),
+            compiler: compilation.compiler.clone(),
+            success: compilation.success,
+            stdout: compilation.stdout.clone(),
+            stderr: compilation.stderr.clone(),
+        }
+    }
+}
+
+impl From<&DiagnosticFinding> for JsonDiagnosticFinding {
+    fn from(finding: &DiagnosticFinding) -> Self {
+        JsonDiagnosticFinding {
+            case: finding.case.clone(),
+            profile: finding.profile.clone(),
+            lint: finding.mismatch.lint.clone(),
+            line: finding.mismatch.line,
+            compiler: finding.mismatch.compiler.clone(),
+            message: finding.mismatch.to_string(),
+        }
+    }
+}
+
+impl From<&AnalysisReport> for JsonResult {
+    fn from(report: &AnalysisReport) -> Self {
+        let (status, diffs, error, compilers) = match &report.result {
+            Ok(()) => ("passed", Vec::new(), None, Vec::new()),
+            Err(AnalysisError::ComparisonFailed(result, _)) => (
+                "failed",
+                result
+                    .differences
+                    .iter()
+                    .map(|diff| JsonDiff::new(diff, &result.compilers))
+                    .collect(),
+                None,
+                result.compilers.to_vec(),
+            ),
+            Err(e @ AnalysisError::Execution(_)) => {
+                ("error", Vec::new(), Some(e.to_string()), Vec::new())
+            }
+        };
+
+        JsonResult {
+            case: report.case.clone(),
+            profile: report.profile.clone(),
+            status: status.to_string(),
+            duration_secs: report.duration.as_secs_f64(),
+            diffs,
+            error,
+            compilers,
+            baseline: report.baseline.map(|status| status.name().to_string()),
+        }
+    }
+}
+
+impl JsonDiff {
+    /// Puts the values of the first of `compilers` under `left` and those of
+    /// the second under `right`.
+    pub fn new(diff: &Diff, compilers: &[String; 2]) -> Self {
+        let (left, right, expected) = match diff {
+            Diff::ExitCode(left, right) => (termination(left), termination(right), None),
+            Diff::Stdout(left, right) | Diff::Stderr(left, right) => (
+                Value::from(left.as_str()),
+                Value::from(right.as_str()),
+                None,
+            ),
+            Diff::Timeout(left, right) => (Value::from(*left), Value::from(*right), None),
+            Diff::StdoutTokens(mismatches) => {
+                let side = |token: fn(&TokenMismatch) -> &Option<String>| {
+                    mismatches
+                        .iter()
+                        .map(|mismatch| json!({ "line": mismatch.line, "token": token(mismatch) }))
+                        .collect::<Value>()
+                };
+                (side(|m| &m.left), side(|m| &m.right), None)
+            }
+            Diff::ExpectedStdout {
+                compiler,
+                expected,
+                actual,
+            }
+            | Diff::ExpectedExitCode {
+                compiler,
+                expected,
+                actual,
+            } => {
+                let actual = Value::from(actual.as_str());
+                let (left, right) = if *compiler == compilers[0] {
+                    (actual, Value::Null)
+                } else {
+                    (Value::Null, actual)
+                };
+                (left, right, Some(Value::from(expected.as_str())))
+            }
+            Diff::Rejection { left, right } => {
+                let side = |diagnostics: &Option<String>| {
+                    json!({
+                        "rejected": diagnostics.is_some(),
+                        "diagnostics": diagnostics,
+                    })
+                };
+                (side(left), side(right), None)
+            }
+            Diff::ErrorMismatch {
+                compiler,
+                missing,
+                diagnostics,
+            } => {
+                let actual = Value::from(diagnostics.as_str());
+                let (left, right) = if *compiler == compilers[0] {
+                    (actual, Value::Null)
+                } else {
+                    (Value::Null, actual)
+                };
+                (left, right, Some(Value::from(missing.clone())))
+            }
+        };
+
+        JsonDiff {
+            kind: diff.kind().to_string(),
+            left,
+            right,
+            expected,
+        }
+    }
+}
+
+fn termination(termination: &Termination) -> Value {
+    json!({
+        "category": termination.kind.name(),
+        "limit": match termination.kind {
+            TerminationKind::LimitExceeded(resource) => Some(resource.name()),
+            _ => None,
+        },
+        "exit_code": termination.exit_code,
+        "signal": termination.signal,
+        "core_dumped": termination.core_dumped,
+    })
+}
@@ -0,0 +1,312 @@
+use super::error_reporter::ErrorReporter;
+use super::report::{AnalysisReport, CompilerInvocation, DiagnosticFinding, Report};
+use crate::analysis::{format_mismatches, format_rejection, AnalysisError, Diff};
+use similar::TextDiff;
+use std::fmt::Write;
+
+impl Report {
+    /// Renders the report as JUnit XML: one `<testsuite>` per test case and
+    /// one `<testcase>` per build profile. Compilation errors are reported as
+    /// an extra `compile` testcase of their suite, compiler diagnostics go to
+    /// the `<system-err>` of their profile's testcase.
+    pub fn to_junit(&self) -> String {
+        let mut suites: Vec<Suite> = Vec::new();
+        let mut orphan_errors = Vec::new();
+
+        for result in &self.analysis_reports {
+            suite_for(&mut suites, &result.case).results.push(result);
+        }
+        for compilation in &self.compilations {
+            suite_for(&mut suites, &compilation.case)
+                .compilations
+                .push(compilation);
+        }
+        for finding in &self.diagnostic_findings {
+            suite_for(&mut suites, &finding.case).findings.push(finding);
+        }
+        for error in &self.errors {
+            match error {
+                ErrorReporter::Compilation { case, .. }
+                | ErrorReporter::CompilerTimeout { case, .. }
+                | ErrorReporter::CompilerCrash { case, .. }
+                | ErrorReporter::Golden { case, .. } => {
+                    suite_for(&mut suites, case).errors.push(error)
+                }
+                _ => orphan_errors.push(error),
+            }
+        }
+
+        let failures = self.failed_tests;
+        let errors = self.errors.len();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"semantic-equivalence-validator\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.6}\">",
+            self.total_tests + errors,
+            failures,
+            errors,
+            self.start_time.elapsed().as_secs_f64()
+        );
+        for suite in &suites {
+            suite.write(&mut xml);
+        }
+        if !orphan_errors.is_empty() {
+            Suite {
+                name: "errors".to_string(),
+                results: Vec::new(),
+                errors: orphan_errors,
+                compilations: Vec::new(),
+                findings: Vec::new(),
+            }
+            .write(&mut xml);
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+}
+
+struct Suite<'a> {
+    name: String,
+    results: Vec<&'a AnalysisReport>,
+    errors: Vec<&'a ErrorReporter>,
+    compilations: Vec<&'a CompilerInvocation>,
+    findings: Vec<&'a DiagnosticFinding>,
+}
+
+fn suite_for<'a, 'b>(suites: &'b mut Vec<Suite<'a>>, case: &str) -> &'b mut Suite<'a> {
+    let index = match suites.iter().position(|suite| suite.name == case) {
+        Some(index) => index,
+        None => {
+            suites.push(Suite {
+                name: case.to_string(),
+                results: Vec::new(),
+                errors: Vec::new(),
+                compilations: Vec::new(),
+                findings: Vec::new(),
+            });
+            suites.len() - 1
+        }
+    };
+    &mut suites[index]
+}
+
+impl Suite<'_> {
+    fn write(&self, xml: &mut String) {
+        let failures = self
+            .results
+            .iter()
+            .filter(|r| matches!(r.result, Err(AnalysisError::ComparisonFailed(..))))
+            .count();
+        let execution_errors = self
+            .results
+            .iter()
+            .filter(|r| matches!(r.result, Err(AnalysisError::Execution(_))))
+            .count();
+        let time = self
+            .results
+            .iter()
+            .fold(0.0, |total, r| total + r.duration.as_secs_f64());
+
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.6}\">",
+            escape(&self.name),
+            self.results.len() + self.errors.len(),
+            failures,
+            execution_errors + self.errors.len(),
+            time
+        );
+
+        for result in &self.results {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.6}\"",
+                escape(&result.profile),
+                escape(&self.name),
+                result.duration.as_secs_f64()
+            );
+            let mut body = String::new();
+            match &result.result {
+                Ok(()) => {}
+                Err(AnalysisError::ComparisonFailed(comparison, _)) => {
+                    let kinds: Vec<_> = comparison.differences.iter().map(Diff::kind).collect();
+                    let diffs: Vec<_> = comparison
+                        .differences
+                        .iter()
+                        .map(|diff| plain_diff(diff, &comparison.compilers))
+                        .collect();
+                    let _ = writeln!(
+                        body,
+                        "      <failure type=\"comparison\" message=\"Differences in {}\">{}</failure>",
+                        kinds.join(", "),
+                        escape(&diffs.join("\n"))
+                    );
+                }
+                Err(e @ AnalysisError::Execution(_)) => {
+                    let _ = writeln!(
+                        body,
+                        "      <error type=\"execution\" message=\"{}\"/>",
+                        escape(&e.to_string())
+                    );
+                }
+            }
+
+            let findings: Vec<_> = self
+                .findings
+                .iter()
+                .filter(|finding| result.build_profiles.contains(&finding.profile))
+                .map(|finding| format!("warning: {}\n", finding.mismatch))
+                .collect();
+            if !findings.is_empty() {
+                let _ = writeln!(
+                    body,
+                    "      <system-out>{}</system-out>",
+                    escape(&findings.concat())
+                );
+            }
+            let diagnostics: Vec<_> = self
+                .compilations
+                .iter()
+                .filter(|c| result.build_profiles.contains(&c.profile) && !c.stderr.is_empty())
+                .map(|c| {
+                    if result.build_profiles[0] == result.build_profiles[1] {
+                        format!("=== {} ===\n{}", c.compiler, c.stderr)
+                    } else {
+                        format!("=== {} {} ===\n{}", c.compiler, c.profile, c.stderr)
+                    }
+                })
+                .collect();
+            if !diagnostics.is_empty() {
+                let _ = writeln!(
+                    body,
+                    "      <system-err>{}</system-err>",
+                    escape(&diagnostics.concat())
+                );
+            }
+
+            if body.is_empty() {
+                xml.push_str("/>\n");
+            } else {
+                let _ = write!(xml, ">\n{}    </testcase>\n", body);
+            }
+        }
+
+        for error in &self.errors {
+            let (name, kind) = match error {
+                ErrorReporter::Compilation { compiler, .. } => {
+                    (format!("compile ({})", compiler), "compilation")
+                }
+                ErrorReporter::CompilerTimeout {
+                    compiler, profile, ..
+                } => (
+                    format!("compile ({}, {})", compiler, profile),
+                    "compiler-timeout",
+                ),
+                ErrorReporter::CompilerCrash {
+                    compiler, profile, ..
+                } => (
+                    format!("compile ({}, {})", compiler, profile),
+                    "compiler-crash",
+                ),
+                ErrorReporter::Analysis(_) => ("analysis".to_string(), "analysis"),
+                ErrorReporter::Workspace(_) => ("workspace".to_string(), "workspace"),
+                ErrorReporter::Golden { .. } => ("bless".to_string(), "golden"),
+            };
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"0\">\n      <error type=\"{}\">{}</error>\n    </testcase>",
+                escape(&name),
+                escape(&self.name),
+                kind,
+                escape(&error.to_string())
+            );
+        }
+
+        xml.push_str("  </testsuite>\n");
+    }
+}
+
+/// Uncolored rendering of a difference, suited for CI dashboards.
+fn plain_diff(diff: &Diff, [left, right]: &[String; 2]) -> String {
+    match diff {
+        Diff::ExitCode(left_exit, right_exit) => {
+            format!(
+                "=== Exit Code Diff ===\n{}: {}\n{}: {}\n",
+                left, left_exit, right, right_exit
+            )
+        }
+        Diff::Timeout(left_timed_out, right_timed_out) => format!(
+            "=== Timeout Diff ===\n{} timed out: {}\n{} timed out: {}\n",
+            left, left_timed_out, right, right_timed_out
+        ),
+        Diff::StdoutTokens(mismatches) => format!(
+            "=== Stdout Diff (float tolerance) ===\n{}",
+            format_mismatches(mismatches, [left, right])
+        ),
+        Diff::ExpectedExitCode {
+            compiler,
+            expected,
+            actual,
+        } => format!(
+            "=== Exit Code Diff ({} vs expected) ===\n{}: {}\nexpected: {}\n",
+            compiler, compiler, actual, expected
+        ),
+        Diff::ExpectedStdout {
+            compiler,
+            expected,
+            actual,
+        } => format!(
+            "=== Stdout Diff ({} vs expected) ===\n{}",
+            compiler,
+            TextDiff::from_lines(actual, expected)
+                .unified_diff()
+                .header(compiler, "expected")
+        ),
+        Diff::Rejection { .. } => {
+            format!(
+                "=== Compile-Fail Diff ===\n{}",
+                format_rejection(diff, [left, right])
+            )
+        }
+        Diff::ErrorMismatch {
+            compiler,
+            missing,
+            diagnostics,
+        } => format!(
+            "=== Error Diff ({} vs expected) ===\nmissing: {}\n{}",
+            compiler,
+            missing.join(", "),
+            diagnostics
+        ),
+        Diff::Stdout(left_output, right_output) | Diff::Stderr(left_output, right_output) => {
+            format!(
+                "=== {} Diff ===\n{}",
+                if matches!(diff, Diff::Stdout(..)) {
+                    "Stdout"
+                } else {
+                    "Stderr"
+                },
+                TextDiff::from_lines(left_output, right_output)
+                    .unified_diff()
+                    .header(left, right)
+            )
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not allowed in XML 1.0.
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1,5 +1,12 @@
+mod baseline;
+mod compare;
 mod error_reporter;
+mod json;
+mod junit;
 mod report;
 
+pub use baseline::{Baseline, BaselineError, BaselineStatus};
+pub use compare::{ReportComparison, SlowdownThreshold};
 pub use error_reporter::ErrorReporter;
-pub use report::Report;
+pub use json::JsonReport;
+pub use report::{CompilerInvocation, Report};
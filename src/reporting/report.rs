@@ -1,5 +1,6 @@
+use super::baseline::{Baseline, BaselineStatus};
 use super::error_reporter::ErrorReporter;
-use crate::analysis::AnalysisError;
+use crate::analysis::{AnalysisError, DiagnosticMismatch};
 use colored::*;
 use log::info;
 use std::time::{Duration, Instant};
@@ -11,14 +12,49 @@ pub struct Report {
     pub failed_tests: usize,
     pub errors: Vec<ErrorReporter>,
     pub analysis_reports: Vec<AnalysisReport>,
-    start_time: Instant,
+    pub compilations: Vec<CompilerInvocation>,
+    /// Warnings only one compiler emitted. They do not fail a test.
+    pub diagnostic_findings: Vec<DiagnosticFinding>,
+    pub start_time: Instant,
+}
+
+/// What a compiler printed while building a test case with one profile.
+#[derive(Debug)]
+pub struct CompilerInvocation {
+    pub case: String,
+    pub profile: String,
+    pub compiler: String,
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug)]
+pub struct DiagnosticFinding {
+    pub case: String,
+    pub profile: String,
+    pub mismatch: DiagnosticMismatch,
 }
 
 #[derive(Debug)]
 pub struct AnalysisReport {
     pub test_name: String,
+    pub case: String,
+    /// What the result is reported under, see `Session::result_label`.
+    pub profile: String,
+    /// The profiles the two compared binaries were built with, which differ
+    /// only in self-diff mode.
+    pub build_profiles: [String; 2],
     pub result: Result<(), AnalysisError>,
     pub duration: Duration,
+    /// Set once the report is checked against a baseline.
+    pub baseline: Option<BaselineStatus>,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Report {
@@ -29,13 +65,17 @@ impl Report {
             failed_tests: 0,
             errors: Vec::new(),
             analysis_reports: Vec::new(),
+            compilations: Vec::new(),
+            diagnostic_findings: Vec::new(),
             start_time: Instant::now(),
         }
     }
 
     pub fn add_result(
         &mut self,
-        test_name: String,
+        case: &str,
+        profile: &str,
+        build_profiles: [&str; 2],
         result: Result<(), AnalysisError>,
         duration: Duration,
     ) {
@@ -45,9 +85,13 @@ impl Report {
             Err(_) => self.failed_tests += 1,
         }
         self.analysis_reports.push(AnalysisReport {
-            test_name,
+            test_name: format!("{} with {}", case, profile),
+            case: case.to_string(),
+            profile: profile.to_string(),
+            build_profiles: build_profiles.map(str::to_string),
             result,
             duration,
+            baseline: None,
         });
     }
 
@@ -55,6 +99,79 @@ impl Report {
         self.errors.push(error);
     }
 
+    /// Internal compiler errors and compilers killed by a signal. These are
+    /// bugs whatever the test case expected, so they fail the run.
+    pub fn compiler_crashes(&self) -> usize {
+        self.errors
+            .iter()
+            .filter(|error| matches!(error, ErrorReporter::CompilerCrash { .. }))
+            .count()
+    }
+
+    /// Compilers that hung on a test case, which fail the run like crashes.
+    pub fn compiler_timeouts(&self) -> usize {
+        self.errors
+            .iter()
+            .filter(|error| matches!(error, ErrorReporter::CompilerTimeout { .. }))
+            .count()
+    }
+
+    pub fn add_compilation(&mut self, compilation: CompilerInvocation) {
+        self.compilations.push(compilation);
+    }
+
+    pub fn add_diagnostic_finding(
+        &mut self,
+        case: &str,
+        profile: &str,
+        mismatch: DiagnosticMismatch,
+    ) {
+        self.diagnostic_findings.push(DiagnosticFinding {
+            case: case.to_string(),
+            profile: profile.to_string(),
+            mismatch,
+        });
+    }
+
+    /// Appends the results and errors collected in `other` after our own.
+    pub fn merge(&mut self, other: Report) {
+        self.total_tests += other.total_tests;
+        self.passed_tests += other.passed_tests;
+        self.failed_tests += other.failed_tests;
+        self.errors.extend(other.errors);
+        self.analysis_reports.extend(other.analysis_reports);
+        self.compilations.extend(other.compilations);
+        self.diagnostic_findings.extend(other.diagnostic_findings);
+    }
+
+    pub fn apply_baseline(&mut self, baseline: &Baseline) {
+        for result in &mut self.analysis_reports {
+            result.baseline = Some(baseline.classify(result));
+        }
+    }
+
+    /// Failures that fail the run: every failed result, or with a baseline
+    /// applied only those it does not list, and every compiler crash or hang.
+    pub fn failures(&self) -> usize {
+        let failed_results = self
+            .analysis_reports
+            .iter()
+            .filter(|result| match result.baseline {
+                Some(status) => status == BaselineStatus::NewFailure,
+                None => result.result.is_err(),
+            })
+            .count();
+        failed_results + self.compiler_crashes() + self.compiler_timeouts()
+    }
+
+    /// Number of results classified as `status` by the baseline.
+    pub fn count(&self, status: BaselineStatus) -> usize {
+        self.analysis_reports
+            .iter()
+            .filter(|result| result.baseline == Some(status))
+            .count()
+    }
+
     pub fn print_summary(&self) {
         info!("Testing complete. Summary below:");
 
@@ -89,9 +206,13 @@ impl Report {
             println!("{}", "-".repeat(40).dimmed());
             for result in &self.analysis_reports {
                 if let Err(e) = &result.result {
+                    let status = match result.baseline {
+                        Some(status) => format!(" [{}]", status.name()),
+                        None => String::new(),
+                    };
                     println!(
                         "{}",
-                        format!("Error report for '{}': ", result.test_name).bold()
+                        format!("Error report for '{}':{} ", result.test_name, status).bold()
                     );
                     match e {
                         AnalysisError::Execution(exec_error) => {
@@ -109,6 +230,20 @@ impl Report {
             }
         }
 
+        if !self.diagnostic_findings.is_empty() {
+            println!("{}", "Diagnostic Findings:".bold().underline().yellow());
+            for finding in &self.diagnostic_findings {
+                println!(
+                    "{} {} with {}: {}",
+                    "warning:".yellow(),
+                    finding.case.bold(),
+                    finding.profile,
+                    finding.mismatch
+                );
+            }
+            println!("{}", "-".repeat(40).dimmed());
+        }
+
         println!("{}", "Test Summary:".bold().underline().green());
         println!(
             "{} {}",
@@ -125,6 +260,39 @@ impl Report {
             "Failed tests:".bold(),
             self.failed_tests.to_string().red()
         );
+        if !self.diagnostic_findings.is_empty() {
+            println!(
+                "{} {}",
+                "Diagnostic findings:".bold(),
+                self.diagnostic_findings.len().to_string().yellow()
+            );
+        }
+        if self.analysis_reports.iter().any(|r| r.baseline.is_some()) {
+            println!(
+                "{} {}",
+                "Expected failures:".bold(),
+                self.count(BaselineStatus::ExpectedFail)
+                    .to_string()
+                    .yellow()
+            );
+            println!(
+                "{} {}",
+                "Unexpected passes:".bold(),
+                self.count(BaselineStatus::UnexpectedPass)
+                    .to_string()
+                    .cyan()
+            );
+            println!(
+                "{} {}",
+                "New failures:".bold(),
+                self.count(BaselineStatus::NewFailure).to_string().red()
+            );
+            for result in &self.analysis_reports {
+                if result.baseline == Some(BaselineStatus::UnexpectedPass) {
+                    println!("  {} now passes", result.test_name.bold());
+                }
+            }
+        }
         let total_duration = self.start_time.elapsed();
         println!(
             "{} {}",
@@ -134,3 +302,83 @@ impl Report {
         println!("{}", "-".repeat(40).dimmed());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{AnalysisResult, Diff};
+    use crate::reporting::baseline::KnownFailure;
+
+    fn add(report: &mut Report, case: &str, passed: bool) {
+        let result = if passed {
+            Ok(())
+        } else {
+            Err(AnalysisError::ComparisonFailed(
+                AnalysisResult {
+                    differences: vec![Diff::Stdout("1".to_string(), "2".to_string())],
+                    compilers: ["gccrs".to_string(), "rustc".to_string()],
+                },
+                case.to_string(),
+            ))
+        };
+        report.add_result(case, "debug", ["debug"; 2], result, Duration::ZERO);
+    }
+
+    fn crash(case: &str) -> ErrorReporter {
+        ErrorReporter::CompilerCrash {
+            case: case.to_string(),
+            compiler: "gccrs".to_string(),
+            profile: "debug".to_string(),
+            reason: "killed by signal 11 (SIGSEGV)".to_string(),
+            message: String::new(),
+        }
+    }
+
+    fn hang(case: &str) -> ErrorReporter {
+        ErrorReporter::CompilerTimeout {
+            case: case.to_string(),
+            compiler: "gccrs".to_string(),
+            profile: "debug".to_string(),
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn failed_results_fail_the_run() {
+        let mut report = Report::new();
+        add(&mut report, "a", true);
+        assert_eq!(report.failures(), 0);
+        add(&mut report, "b", false);
+        add(&mut report, "c", false);
+        assert_eq!(report.failures(), 2);
+    }
+
+    #[test]
+    fn compiler_crashes_and_hangs_fail_the_run() {
+        let mut report = Report::new();
+        report.add_error(crash("a"));
+        report.add_error(hang("b"));
+        report.add_error(ErrorReporter::Golden {
+            case: "c".to_string(),
+            message: "no expected output".to_string(),
+        });
+        assert_eq!(report.failures(), 2);
+    }
+
+    #[test]
+    fn baseline_excuses_known_failures_only() {
+        let mut report = Report::new();
+        add(&mut report, "known", false);
+        add(&mut report, "new", false);
+        add(&mut report, "fixed", true);
+        report.add_error(hang("known"));
+        report.apply_baseline(&Baseline {
+            known_failures: vec![KnownFailure {
+                case: "known".to_string(),
+                profile: "debug".to_string(),
+                kind: "stdout".to_string(),
+            }],
+        });
+        assert_eq!(report.failures(), 2);
+    }
+}
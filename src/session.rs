@@ -0,0 +1,576 @@
+use crate::analysis::{
+    compare_diagnostics, compare_rejections, AnalysisContext, AnalysisError, CompareOptions,
+    CompileFailExpectation, Diff, ExecutionSettings, Expected, Normalizer,
+};
+use crate::compiler::{
+    comparison_pairs, compile, Backend, BuildProfile, CompileError, CompilerOutput,
+};
+use crate::config::AppConfig;
+use crate::reporting::{CompilerInvocation, ErrorReporter, Report};
+use crate::testsuite::{CaseSource, TestCase};
+use crate::utils;
+use crate::workspace::{BuildWorkspace, CaseWorkspace};
+use log::{info, warn};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Everything shared by the test cases of one run.
+pub struct Session<'a> {
+    pub config: &'a AppConfig,
+    pub workspace: BuildWorkspace,
+    /// The compilers to build with, usually [`AppConfig::backends`].
+    pub backends: Vec<Backend>,
+    pub profiles: Vec<BuildProfile>,
+    /// Write the golden files of each case from the binary of the reference
+    /// compiler before comparing against them.
+    pub bless: bool,
+    /// Compare the binaries each compiler builds with the later profiles
+    /// against the one it builds with the first, instead of comparing the
+    /// compilers with each other. Differences then point at optimizer bugs.
+    pub self_diff: bool,
+}
+
+/// One binary of a test case, as the indices of the compiler and the
+/// profile it was built with.
+#[derive(Debug, Clone, Copy)]
+struct Build {
+    backend: usize,
+    profile: usize,
+}
+
+/// What building a test case once left behind.
+struct BuildOutput {
+    /// `false` if the compiler rejected the program.
+    built: bool,
+    output: CompilerOutput,
+    duration: Duration,
+}
+
+impl Session<'_> {
+    /// Builds and analyzes `cases` on up to `jobs` threads.
+    pub fn analyze_suite(&self, cases: &[TestCase], jobs: usize) -> Report {
+        let mut report = Report::new();
+
+        // Every case is analyzed into its own report so that the merged summary
+        // keeps the suite order regardless of which worker finished first.
+        let case_reports = utils::parallel_map(cases, jobs, |case| {
+            let mut case_report = Report::new();
+            self.compile_and_analyze_case(case, &mut case_report);
+            case_report
+        });
+        for case_report in case_reports {
+            report.merge(case_report);
+        }
+        report
+    }
+
+    /// Builds and analyzes `case` in its own build directory. The directory is
+    /// removed once every build profile passes and kept otherwise.
+    pub fn compile_and_analyze_case(&self, case: &TestCase, report: &mut Report) {
+        let case_workspace = match self.workspace.for_case(case) {
+            Ok(case_workspace) => case_workspace,
+            Err(e) => {
+                report.add_error(e.into());
+                return;
+            }
+        };
+
+        if self.analyze_case(case, &case_workspace, report) {
+            if let Err(e) = case_workspace.cleanup() {
+                report.add_error(e.into());
+            }
+        } else {
+            case_workspace.keep();
+        }
+    }
+
+    /// Returns `true` if the case compiled and passed with every build profile.
+    pub fn analyze_case(
+        &self,
+        case: &TestCase,
+        workspace: &CaseWorkspace,
+        report: &mut Report,
+    ) -> bool {
+        let config = self.config;
+        let sources: Result<Vec<_>, _> = self
+            .backends
+            .iter()
+            .map(|backend| {
+                workspace.source(
+                    case,
+                    backend.dialect,
+                    &backend.name,
+                    backend.prelude.as_deref(),
+                )
+            })
+            .collect();
+        let sources = match sources {
+            Ok(sources) => sources,
+            Err(e) => {
+                report.add_error(e.into());
+                return false;
+            }
+        };
+
+        let Some(outputs) = self.build(case, &sources, workspace, report) else {
+            return false;
+        };
+        if let Some(expectation) = &case.directives.compile_fail {
+            return self.analyze_compile_fail(case, expectation, &outputs, report);
+        }
+
+        info!("Starting analysis for case '{}' ...", case.name);
+
+        let settings = ExecutionSettings {
+            timeout: case
+                .directives
+                .run_timeout
+                .unwrap_or(Duration::from_secs(config.timeouts.run)),
+            output_limit: config.execution.max_output,
+            inputs: case.directives.inputs.clone(),
+            limits: case.directives.limits.or(config.execution.limits),
+        };
+
+        let mut options = CompareOptions {
+            expected: case.expected.clone(),
+            normalizers: self.normalizers(case),
+            stdout_mode: case.directives.stdout_mode,
+            stderr_mode: case
+                .directives
+                .stderr_mode
+                .unwrap_or(config.comparison.stderr),
+            // Set for each compared pair of compilers below.
+            same_layout: false,
+        };
+        if self.bless {
+            match self.bless_case(case, workspace, &settings) {
+                Ok(expected) => options.expected = Some(expected),
+                Err(message) => {
+                    report.add_error(ErrorReporter::Golden {
+                        case: case.name.clone(),
+                        message,
+                    });
+                    return false;
+                }
+            }
+        }
+
+        let mut passed = true;
+        for builds in self.build_pairs() {
+            let [left, right] = builds.map(|build| self.binary(workspace, build));
+            let names = builds.map(|build| self.build_name(build));
+
+            let label = self.result_label(builds);
+            let testname = format!("{} with {}", case.name, label);
+            options.same_layout = self.same_layout(case, builds[0].backend, builds[1].backend);
+            let context = AnalysisContext::new(
+                testname,
+                names.each_ref().map(String::as_str),
+                [&left, &right],
+                &settings,
+                &options,
+            );
+            let start = Instant::now();
+            let result = context.analyze();
+            let duration = start.elapsed();
+            passed &= result.is_ok();
+            report.add_result(
+                &case.name,
+                &label,
+                self.build_profiles(builds),
+                result,
+                duration,
+            );
+        }
+        passed
+    }
+
+    /// Builds `case` with every compiler and profile, recording each
+    /// invocation as it finishes. Returns what the builds printed, indexed
+    /// like [`Build`], or `None` if a compiler failed. Rejecting the program
+    /// only counts as failing outside compile-fail tests.
+    fn build(
+        &self,
+        case: &TestCase,
+        sources: &[PathBuf],
+        workspace: &CaseWorkspace,
+        report: &mut Report,
+    ) -> Option<Vec<Vec<BuildOutput>>> {
+        let compile_fail = case.directives.compile_fail.is_some();
+
+        let mut outputs = Vec::new();
+        let mut failed = false;
+        for (backend, source) in self.backends.iter().zip(sources) {
+            let mut backend_outputs = Vec::new();
+            for profile in &self.profiles {
+                let start = Instant::now();
+                let timeout = self.compile_timeout(case, backend);
+                let output = match compile(backend, source, profile, workspace, timeout) {
+                    Ok(output) => BuildOutput {
+                        built: true,
+                        output,
+                        duration: start.elapsed(),
+                    },
+                    Err(CompileError::Rejected {
+                        stdout, message, ..
+                    }) if compile_fail => BuildOutput {
+                        built: false,
+                        output: CompilerOutput {
+                            stdout,
+                            stderr: message,
+                        },
+                        duration: start.elapsed(),
+                    },
+                    Err(e) => {
+                        if let CompileError::Rejected {
+                            stdout, message, ..
+                        }
+                        | CompileError::Crashed {
+                            stdout, message, ..
+                        } = &e
+                        {
+                            let output = CompilerOutput {
+                                stdout: stdout.clone(),
+                                stderr: message.clone(),
+                            };
+                            record_invocation(case, profile, backend, false, &output, report);
+                        }
+                        // The remaining profiles of this compiler are
+                        // skipped, the other compilers still build.
+                        report.add_error(compile_error(case, backend, e));
+                        failed = true;
+                        break;
+                    }
+                };
+                record_invocation(case, profile, backend, output.built, &output.output, report);
+                backend_outputs.push(output);
+            }
+            outputs.push(backend_outputs);
+        }
+
+        self.compare_warnings(case, &outputs, report);
+        (!failed).then_some(outputs)
+    }
+
+    /// A compile-fail test passes for a profile when every compiler rejects
+    /// it and reports the expected errors. Nothing is executed.
+    fn analyze_compile_fail(
+        &self,
+        case: &TestCase,
+        expectation: &CompileFailExpectation,
+        outputs: &[Vec<BuildOutput>],
+        report: &mut Report,
+    ) -> bool {
+        let normalizers = self.normalizers(case);
+
+        let mut passed = true;
+        for builds in self.build_pairs() {
+            let [left, right] = builds.map(|build| &outputs[build.backend][build.profile]);
+            let names = builds.map(|build| self.build_name(build));
+            let result = compare_rejections(
+                names.each_ref().map(String::as_str),
+                (!left.built).then_some(left.output.stderr.as_str()),
+                (!right.built).then_some(right.output.stderr.as_str()),
+                expectation,
+                &normalizers,
+            );
+
+            let label = self.result_label(builds);
+            let result = if result.is_identical() {
+                Ok(())
+            } else {
+                let testname = format!("{} with {}", case.name, label);
+                Err(AnalysisError::ComparisonFailed(result, testname))
+            };
+            passed &= result.is_ok();
+            report.add_result(
+                &case.name,
+                &label,
+                self.build_profiles(builds),
+                result,
+                left.duration + right.duration,
+            );
+        }
+        passed
+    }
+
+    /// Reports the mapped warnings only one compiler of a compared pair
+    /// emitted with the same profile, when that comparison is enabled.
+    /// Warnings are not compared across optimization levels, and only by
+    /// count when the two compilers built different files of a pair.
+    fn compare_warnings(&self, case: &TestCase, outputs: &[Vec<BuildOutput>], report: &mut Report) {
+        let diagnostics = &self.config.diagnostics;
+        if !diagnostics.compare || self.self_diff {
+            return;
+        }
+        for (index, profile) in self.profiles.iter().enumerate() {
+            for (left, right) in self.comparison_pairs() {
+                // Compilers that failed before this profile have no output.
+                let (Some(left_output), Some(right_output)) =
+                    (outputs[left].get(index), outputs[right].get(index))
+                else {
+                    continue;
+                };
+                let [left_backend, right_backend] = [&self.backends[left], &self.backends[right]];
+                let same_layout = self.same_layout(case, left, right);
+                for mismatch in compare_diagnostics(
+                    [
+                        (&left_backend.name, left_backend.dialect),
+                        (&right_backend.name, right_backend.dialect),
+                    ],
+                    [&left_output.output.stderr, &right_output.output.stderr],
+                    &diagnostics.lints,
+                    same_layout,
+                ) {
+                    report.add_diagnostic_finding(&case.name, &profile.name, mismatch);
+                }
+            }
+        }
+    }
+
+    /// Whether the two compilers build files with the same lines, so that
+    /// line numbers in what they or their binaries print can be compared.
+    fn same_layout(&self, case: &TestCase, left: usize, right: usize) -> bool {
+        matches!(case.source, CaseSource::Single { .. })
+            || self.backends[left].dialect == self.backends[right].dialect
+    }
+
+    fn comparison_pairs(&self) -> Vec<(usize, usize)> {
+        comparison_pairs(&self.backends, self.config.reference())
+    }
+
+    /// The builds whose binaries are compared, the reference on the right:
+    /// every compared pair of compilers with each profile, or in self-diff
+    /// mode every later profile against the first with each compiler.
+    fn build_pairs(&self) -> Vec<[Build; 2]> {
+        if self.self_diff {
+            return (0..self.backends.len())
+                .flat_map(|backend| {
+                    (1..self.profiles.len()).map(move |profile| {
+                        [
+                            Build { backend, profile },
+                            Build {
+                                backend,
+                                profile: 0,
+                            },
+                        ]
+                    })
+                })
+                .collect();
+        }
+
+        let pairs = self.comparison_pairs();
+        (0..self.profiles.len())
+            .flat_map(|profile| {
+                pairs.iter().map(move |&(left, right)| {
+                    [
+                        Build {
+                            backend: left,
+                            profile,
+                        },
+                        Build {
+                            backend: right,
+                            profile,
+                        },
+                    ]
+                })
+            })
+            .collect()
+    }
+
+    fn binary(&self, workspace: &CaseWorkspace, build: Build) -> PathBuf {
+        workspace.binary(
+            &self.backends[build.backend].name,
+            &self.profiles[build.profile],
+        )
+    }
+
+    /// How the binary of `build` is named in diffs.
+    fn build_name(&self, build: Build) -> String {
+        let backend = &self.backends[build.backend].name;
+        if self.self_diff {
+            format!("{} {}", backend, self.profiles[build.profile].name)
+        } else {
+            backend.clone()
+        }
+    }
+
+    fn build_profiles(&self, builds: [Build; 2]) -> [&str; 2] {
+        builds.map(|build| self.profiles[build.profile].name.as_str())
+    }
+
+    /// The profile a result is reported under. It names the compared
+    /// compilers when there is more than one pair, and the compared profiles
+    /// in self-diff mode.
+    fn result_label(&self, [left, right]: [Build; 2]) -> String {
+        let profile = &self.profiles[left.profile].name;
+        let [left_backend, right_backend] =
+            [&self.backends[left.backend], &self.backends[right.backend]];
+        if self.self_diff {
+            let label = format!("{} vs {}", profile, self.profiles[right.profile].name);
+            if self.backends.len() == 1 {
+                label
+            } else {
+                format!("{} ({})", label, left_backend.name)
+            }
+        } else if self.comparison_pairs().len() == 1 {
+            profile.clone()
+        } else {
+            format!(
+                "{} ({} vs {})",
+                profile, left_backend.name, right_backend.name
+            )
+        }
+    }
+
+    fn compile_timeout(&self, case: &TestCase, backend: &Backend) -> Duration {
+        case.directives.compile_timeout.unwrap_or_else(|| {
+            Duration::from_secs(
+                backend
+                    .compile_timeout
+                    .unwrap_or(self.config.timeouts.compile),
+            )
+        })
+    }
+
+    fn normalizers(&self, case: &TestCase) -> Vec<Normalizer> {
+        case.directives
+            .normalize
+            .clone()
+            .unwrap_or_else(|| self.config.normalize.clone())
+    }
+
+    /// Compiles and analyzes `case` with the first profile and returns the
+    /// sorted kinds of differences found between any of the compared pairs,
+    /// or `None` if none of them failed. The case is built under `scratch`
+    /// and removed afterwards.
+    pub fn diff_kinds(
+        &self,
+        case: &TestCase,
+        scratch: &BuildWorkspace,
+    ) -> Option<Vec<&'static str>> {
+        let workspace = scratch.for_case(case).ok()?;
+        let mut report = Report::new();
+        self.analyze_case(case, &workspace, &mut report);
+        if let Err(e) = workspace.cleanup() {
+            warn!("{}", e);
+        }
+
+        let mut kinds: Vec<_> = report
+            .analysis_reports
+            .iter()
+            .filter_map(|analysis| match &analysis.result {
+                Err(AnalysisError::ComparisonFailed(result, _)) => Some(&result.differences),
+                _ => None,
+            })
+            .flatten()
+            .map(Diff::kind)
+            .collect();
+        if kinds.is_empty() {
+            return None;
+        }
+        kinds.sort_unstable();
+        kinds.dedup();
+        Some(kinds)
+    }
+
+    /// Writes the golden files of `case` from the output of the reference
+    /// binary of the first compared pair of builds.
+    fn bless_case(
+        &self,
+        case: &TestCase,
+        workspace: &CaseWorkspace,
+        settings: &ExecutionSettings,
+    ) -> Result<Expected, String> {
+        let builds = *self
+            .build_pairs()
+            .first()
+            .ok_or_else(|| "there are no builds to compare".to_string())?;
+        let [left_binary, right_binary] = builds.map(|build| self.binary(workspace, build));
+        let names = builds.map(|build| self.build_name(build));
+        let right = &names[1];
+        let context = AnalysisContext::new(
+            case.name.clone(),
+            names.each_ref().map(String::as_str),
+            [&left_binary, &right_binary],
+            settings,
+            &CompareOptions::default(),
+        );
+
+        let output = context
+            .run_reference()
+            .map_err(|e| e.to_string())?
+            .output
+            .ok_or_else(|| format!("{} binary timed out", right))?;
+        if output.stdout.truncated {
+            return Err(format!("{} output exceeds the capture limit", right));
+        }
+
+        let expected = Expected {
+            stdout: Some(output.stdout.bytes),
+            exit_code: output.status.code(),
+        };
+        case.golden.bless(&expected).map_err(|e| e.to_string())?;
+        info!(
+            "Blessed '{}' into '{}'",
+            case.name,
+            case.golden.stdout.display()
+        );
+        Ok(expected)
+    }
+}
+
+/// Records what `backend` printed while building `case` with `profile`.
+fn record_invocation(
+    case: &TestCase,
+    profile: &BuildProfile,
+    backend: &Backend,
+    success: bool,
+    output: &CompilerOutput,
+    report: &mut Report,
+) {
+    report.add_compilation(CompilerInvocation {
+        case: case.name.clone(),
+        profile: profile.name.clone(),
+        compiler: backend.name.clone(),
+        success,
+        stdout: output.stdout.clone(),
+        stderr: output.stderr.clone(),
+    });
+}
+
+/// Turns a failure to build `case` with `backend` into its report entry.
+fn compile_error(case: &TestCase, backend: &Backend, e: CompileError) -> ErrorReporter {
+    match e {
+        CompileError::Timeout {
+            profile, timeout, ..
+        } => ErrorReporter::CompilerTimeout {
+            case: case.name.clone(),
+            compiler: backend.name.clone(),
+            profile,
+            timeout,
+        },
+        CompileError::Crashed {
+            profile,
+            reason,
+            message,
+            ..
+        } => ErrorReporter::CompilerCrash {
+            case: case.name.clone(),
+            compiler: backend.name.clone(),
+            profile,
+            reason,
+            message,
+        },
+        CompileError::Rejected { message, .. } => ErrorReporter::Compilation {
+            case: case.name.clone(),
+            compiler: backend.name.clone(),
+            message,
+        },
+        e @ CompileError::Io { .. } => ErrorReporter::Compilation {
+            case: case.name.clone(),
+            compiler: backend.name.clone(),
+            message: e.to_string(),
+        },
+    }
+}
@@ -0,0 +1,332 @@
+use crate::analysis::{
+    CompileFailExpectation, ExecutionInputs, Normalizer, ResourceLimits, StderrMode, StdoutMode,
+};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+const DIRECTIVE_PREFIX: &str = "//@";
+
+#[derive(Debug)]
+pub struct DirectiveError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Per-test settings declared through `//@ key: value` comments in a test
+/// source file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Directives {
+    pub inputs: ExecutionInputs,
+
+    /// Replaces the normalizers from the config file when set. `//@
+    /// normalize: none` turns them off for the test.
+    pub normalize: Option<Vec<Normalizer>>,
+
+    pub stdout_mode: StdoutMode,
+
+    /// Replaces the stderr mode from the config file when set.
+    pub stderr_mode: Option<StderrMode>,
+
+    /// Override the limits from the config file one by one.
+    pub limits: ResourceLimits,
+
+    pub compile_timeout: Option<Duration>,
+    pub run_timeout: Option<Duration>,
+
+    /// Set for tests that both compilers have to reject. Expected error
+    /// codes and messages imply it.
+    pub compile_fail: Option<CompileFailExpectation>,
+}
+
+impl Directives {
+    /// Parses every directive in `source`. Relative paths are resolved
+    /// against `source_dir`.
+    pub fn parse(source: &str, source_dir: &Path) -> Result<Self, DirectiveError> {
+        let mut directives = Self::default();
+
+        for (index, line) in source.lines().enumerate() {
+            let Some(directive) = line.trim_start().strip_prefix(DIRECTIVE_PREFIX) else {
+                continue;
+            };
+            let line = index + 1;
+            let error = |message: String| DirectiveError { line, message };
+
+            let (key, value) = match directive.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (directive.trim(), ""),
+            };
+
+            match key {
+                // Handled by the single-source preprocessor.
+                "end" => {}
+                _ if key.starts_with("cfg(") => {}
+
+                "arg" => directives.inputs.args.push(value.to_string()),
+                "env" => {
+                    let (name, value) = value
+                        .split_once('=')
+                        .filter(|(name, _)| !name.trim().is_empty())
+                        .ok_or_else(|| {
+                            error(format!("expected `NAME=VALUE`, found '{}'", value))
+                        })?;
+                    directives
+                        .inputs
+                        .env
+                        .push((name.trim().to_string(), value.to_string()));
+                }
+                "stdin" => {
+                    directives.inputs.stdin.extend_from_slice(value.as_bytes());
+                    directives.inputs.stdin.push(b'\n');
+                }
+                "stdin-file" => {
+                    let path = source_dir.join(value);
+                    let content = fs::read(&path).map_err(|e| {
+                        error(format!(
+                            "cannot read stdin file '{}': {}",
+                            path.display(),
+                            e
+                        ))
+                    })?;
+                    directives.inputs.stdin.extend(content);
+                }
+                "normalize" => {
+                    let normalizers = directives.normalize.get_or_insert_with(Vec::new);
+                    if value != "none" {
+                        normalizers.push(value.parse().map_err(error)?);
+                    }
+                }
+                "stdout-mode" => directives.stdout_mode = value.parse().map_err(error)?,
+                "stderr-mode" => directives.stderr_mode = Some(value.parse().map_err(error)?),
+                "limit" => directives.limits.set(value).map_err(error)?,
+                "compile-timeout" => {
+                    directives.compile_timeout = Some(parse_seconds(value).map_err(error)?)
+                }
+                "run-timeout" => {
+                    directives.run_timeout = Some(parse_seconds(value).map_err(error)?)
+                }
+                "compile-fail" => {
+                    directives.compile_fail.get_or_insert_with(Default::default);
+                }
+                "error-code" => {
+                    if !is_error_code(value) {
+                        return Err(error(format!(
+                            "expected an error code such as `E0308`, found '{}'",
+                            value
+                        )));
+                    }
+                    directives
+                        .compile_fail
+                        .get_or_insert_with(Default::default)
+                        .error_codes
+                        .push(value.to_string())
+                }
+                "error-message" => directives
+                    .compile_fail
+                    .get_or_insert_with(Default::default)
+                    .messages
+                    .push(value.to_string()),
+                _ => return Err(error(format!("unknown directive '{}'", key))),
+            }
+        }
+
+        Ok(directives)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// `E` followed by four digits, as rustc and gccrs number their errors.
+fn is_error_code(value: &str) -> bool {
+    value
+        .strip_prefix('E')
+        .is_some_and(|digits| digits.len() == 4 && digits.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse()
+        .map(Duration::from_secs)
+        .map_err(|_| format!("expected a number of seconds, found '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::FloatTolerance;
+
+    fn parse(source: &str) -> Result<Directives, DirectiveError> {
+        Directives::parse(source, Path::new("/nonexistent"))
+    }
+
+    fn error(source: &str) -> (usize, String) {
+        let error = parse(source).unwrap_err();
+        (error.line, error.message)
+    }
+
+    #[test]
+    fn parses_each_directive() {
+        let cases: &[(&str, Directives)] = &[
+            (
+                "//@ arg: --verbose\n//@ arg:",
+                Directives {
+                    inputs: ExecutionInputs {
+                        args: vec!["--verbose".to_string(), String::new()],
+                        ..ExecutionInputs::default()
+                    },
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ env: NAME = a=b",
+                Directives {
+                    inputs: ExecutionInputs {
+                        env: vec![("NAME".to_string(), " a=b".to_string())],
+                        ..ExecutionInputs::default()
+                    },
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ stdin: 1 2\n//@ stdin: 3",
+                Directives {
+                    inputs: ExecutionInputs {
+                        stdin: b"1 2\n3\n".to_vec(),
+                        ..ExecutionInputs::default()
+                    },
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ normalize: hex-addresses",
+                Directives {
+                    normalize: Some(vec![Normalizer::HexAddresses]),
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ normalize: none",
+                Directives {
+                    normalize: Some(Vec::new()),
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ stdout-mode: float",
+                Directives {
+                    stdout_mode: StdoutMode::Float(FloatTolerance::default()),
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ stderr-mode: exact",
+                Directives {
+                    stderr_mode: Some(StderrMode::Exact),
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ limit: cpu-time=2",
+                Directives {
+                    limits: ResourceLimits {
+                        cpu_time: Some(2),
+                        ..ResourceLimits::default()
+                    },
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ compile-timeout: 30\n//@ run-timeout: 5",
+                Directives {
+                    compile_timeout: Some(Duration::from_secs(30)),
+                    run_timeout: Some(Duration::from_secs(5)),
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ compile-fail",
+                Directives {
+                    compile_fail: Some(CompileFailExpectation::default()),
+                    ..Directives::default()
+                },
+            ),
+            (
+                "//@ error-code: E0308\n//@ error-message: mismatched   types",
+                Directives {
+                    compile_fail: Some(CompileFailExpectation {
+                        error_codes: vec!["E0308".to_string()],
+                        messages: vec!["mismatched   types".to_string()],
+                    }),
+                    ..Directives::default()
+                },
+            ),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(&parse(source).unwrap(), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn ignores_preprocessor_markers_and_plain_comments() {
+        let directives = parse("//@ cfg(gccrs)\n// arg: x\nfn main() {}\n//@ end\n").unwrap();
+        assert!(directives.is_empty());
+    }
+
+    #[test]
+    fn reads_stdin_files_relative_to_the_source() {
+        let dir = std::env::temp_dir().join(format!("sev-directives-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("input.txt"), "42").unwrap();
+        let directives = Directives::parse("//@ stdin-file: input.txt", &dir);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(directives.unwrap().inputs.stdin, b"42");
+    }
+
+    #[test]
+    fn rejects_malformed_directives() {
+        let cases: &[(&str, usize, &str)] = &[
+            (
+                "fn main() {}\n//@ run-pass",
+                2,
+                "unknown directive 'run-pass'",
+            ),
+            ("//@ env: NAME", 1, "expected `NAME=VALUE`, found 'NAME'"),
+            (
+                "//@ env: =value",
+                1,
+                "expected `NAME=VALUE`, found '=value'",
+            ),
+            ("//@ limit: cpu=1", 1, "unknown resource 'cpu'"),
+            ("//@ limit: cpu-time=lots", 1, "invalid limit 'lots'"),
+            (
+                "//@ error-code: 308",
+                1,
+                "expected an error code such as `E0308`, found '308'",
+            ),
+            (
+                "//@ error-code: E03O8",
+                1,
+                "expected an error code such as `E0308`, found 'E03O8'",
+            ),
+            (
+                "//@ run-timeout: 1.5",
+                1,
+                "expected a number of seconds, found '1.5'",
+            ),
+            ("//@ stderr-mode: loud", 1, "unknown stderr mode 'loud'"),
+            (
+                "//@ normalize: everything",
+                1,
+                "unknown normalizer 'everything'",
+            ),
+        ];
+        for (source, line, message) in cases {
+            assert_eq!(error(source), (*line, message.to_string()), "{}", source);
+        }
+        assert!(error("//@ stdin-file: missing.txt")
+            .1
+            .starts_with("cannot read stdin file '/nonexistent/missing.txt'"));
+    }
+}
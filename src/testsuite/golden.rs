@@ -0,0 +1,73 @@
+use super::TestSuiteError;
+use crate::analysis::Expected;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Optional files holding the output a test case is expected to produce,
+/// stored next to its source as `<name>.stdout.expected` and
+/// `<name>.exitcode.expected`.
+#[derive(Debug, Clone)]
+pub struct GoldenFiles {
+    pub stdout: PathBuf,
+    pub exit_code: PathBuf,
+}
+
+impl GoldenFiles {
+    pub fn next_to(source: &Path, name: &str) -> Self {
+        let dir = source.parent().unwrap_or(Path::new("."));
+        Self {
+            stdout: dir.join(format!("{}.stdout.expected", name)),
+            exit_code: dir.join(format!("{}.exitcode.expected", name)),
+        }
+    }
+
+    /// Returns `None` when the test case has no golden files at all.
+    pub fn load(&self) -> Result<Option<Expected>, TestSuiteError> {
+        let stdout = read_optional(&self.stdout)?;
+        let exit_code = match read_optional(&self.exit_code)? {
+            Some(content) => {
+                let content = String::from_utf8_lossy(&content);
+                let code = content
+                    .trim()
+                    .parse()
+                    .map_err(|_| TestSuiteError::InvalidGolden {
+                        file: self.exit_code.clone(),
+                        message: format!(
+                            "expected an integer exit code, found '{}'",
+                            content.trim()
+                        ),
+                    })?;
+                Some(code)
+            }
+            None => None,
+        };
+
+        if stdout.is_none() && exit_code.is_none() {
+            Ok(None)
+        } else {
+            Ok(Some(Expected { stdout, exit_code }))
+        }
+    }
+
+    /// Writes the golden files. The exit code file is removed when the
+    /// reference was terminated by a signal.
+    pub fn bless(&self, expected: &Expected) -> io::Result<()> {
+        if let Some(stdout) = &expected.stdout {
+            fs::write(&self.stdout, stdout)?;
+        }
+        match expected.exit_code {
+            Some(code) => fs::write(&self.exit_code, format!("{}\n", code)),
+            None if self.exit_code.exists() => fs::remove_file(&self.exit_code),
+            None => Ok(()),
+        }
+    }
+}
+
+fn read_optional(path: &Path) -> Result<Option<Vec<u8>>, TestSuiteError> {
+    match fs::read(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
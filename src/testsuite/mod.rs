@@ -1,20 +1,98 @@
+mod directives;
+mod golden;
+mod preprocess;
+
+use crate::analysis::Expected;
+use crate::compiler::CompilerKind;
 use crate::utils;
+pub use directives::Directives;
+pub use golden::GoldenFiles;
+use preprocess::preprocess;
+use std::fs;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 
+#[derive(Debug, Clone)]
+pub enum CaseSource {
+    /// Separate source files for rustc and gccrs.
+    Pair { rustc: PathBuf, gccrs: PathBuf },
+    /// One source file, already split into the variant each compiler builds.
+    Single { rustc: String, gccrs: String },
+}
+
 #[derive(Debug, Clone)]
 pub struct TestCase {
     pub name: String,
-    pub rustc: PathBuf,
-    pub gccrs: PathBuf,
+    pub source: CaseSource,
+    pub directives: Directives,
+    pub golden: GoldenFiles,
+    pub expected: Option<Expected>,
 }
 
 impl TestCase {
-    pub fn new(name: String, rustc: PathBuf, gccrs: PathBuf) -> Self {
-        Self { name, rustc, gccrs }
+    /// Directives may be declared in either file of the pair, but if both
+    /// declare some they have to agree.
+    pub fn from_pair(name: String, rustc: PathBuf, gccrs: PathBuf) -> Result<Self, TestSuiteError> {
+        let rustc_directives = read_directives(&rustc)?;
+        let gccrs_directives = read_directives(&gccrs)?;
+
+        let directives = if rustc_directives.is_empty() {
+            gccrs_directives
+        } else if gccrs_directives.is_empty() || gccrs_directives == rustc_directives {
+            rustc_directives
+        } else {
+            return Err(TestSuiteError::ConflictingDirectives(name));
+        };
+
+        let golden = GoldenFiles::next_to(&rustc, &name);
+        Ok(Self {
+            expected: golden.load()?,
+            golden,
+            name,
+            source: CaseSource::Pair { rustc, gccrs },
+            directives,
+        })
+    }
+
+    pub fn from_source(name: String, path: &Path) -> Result<Self, TestSuiteError> {
+        let content = fs::read_to_string(path)?;
+        let split = |compiler| {
+            preprocess(&content, compiler).map_err(|e| TestSuiteError::Directive {
+                file: path.to_path_buf(),
+                line: e.line,
+                message: e.message,
+            })
+        };
+        let source = CaseSource::Single {
+            rustc: split(CompilerKind::Rustc)?,
+            gccrs: split(CompilerKind::Gccrs)?,
+        };
+        let directives = parse_directives(&content, path)?;
+        let golden = GoldenFiles::next_to(path, &name);
+        Ok(Self {
+            expected: golden.load()?,
+            golden,
+            name,
+            source,
+            directives,
+        })
     }
 }
 
+fn read_directives(path: &Path) -> Result<Directives, TestSuiteError> {
+    let content = fs::read_to_string(path)?;
+    parse_directives(&content, path)
+}
+
+fn parse_directives(content: &str, path: &Path) -> Result<Directives, TestSuiteError> {
+    let source_dir = path.parent().unwrap_or(Path::new("."));
+    Directives::parse(content, source_dir).map_err(|e| TestSuiteError::Directive {
+        file: path.to_path_buf(),
+        line: e.line,
+        message: e.message,
+    })
+}
+
 pub struct TestSuite {
     pub cases: Vec<TestCase>,
     pub size: usize,
@@ -30,6 +108,28 @@ pub enum TestSuiteError {
 
     #[error("Invalid path: {0}")]
     InvalidPath(String),
+
+    #[error("{}:{line}: {message}", file.display())]
+    Directive {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+
+    #[error("The rustc and gccrs sources of '{0}' declare different directives")]
+    ConflictingDirectives(String),
+
+    #[error("Invalid golden file '{}': {message}", file.display())]
+    InvalidGolden { file: PathBuf, message: String },
+}
+
+/// Test sources are the `.rs` files of a directory, golden files and other
+/// sidecars next to them are skipped.
+fn rust_files_in_dir(dir: &Path) -> Result<Vec<PathBuf>, TestSuiteError> {
+    Ok(utils::get_files_in_dir(dir)?
+        .into_iter()
+        .filter(|file| file.extension().is_some_and(|ext| ext == "rs"))
+        .collect())
 }
 
 fn extract_test_name(file_path: &Path) -> Option<String> {
@@ -49,7 +149,8 @@ impl TestSuite {
         let test_name = extract_test_name(rustc_src)
             .ok_or_else(|| TestSuiteError::InvalidPath("Invalid file name".to_string()))?;
 
-        let case = TestCase::new(test_name, rustc_src.to_path_buf(), gccrs_src.to_path_buf());
+        let case =
+            TestCase::from_pair(test_name, rustc_src.to_path_buf(), gccrs_src.to_path_buf())?;
         let cases = vec![case];
         Ok(Self { cases, size: 1 })
     }
@@ -70,8 +171,8 @@ impl TestSuite {
             return Err(TestSuiteError::InvalidPath(msg));
         }
 
-        let rustc_files = utils::get_files_in_dir(&rustc_dir)?;
-        let gccrs_files = utils::get_files_in_dir(&gccrs_dir)?;
+        let rustc_files = rust_files_in_dir(&rustc_dir)?;
+        let gccrs_files = rust_files_in_dir(&gccrs_dir)?;
 
         let rustc_file_map: std::collections::HashMap<String, PathBuf> = rustc_files
             .into_iter()
@@ -93,7 +194,7 @@ impl TestSuite {
         let mut cases = Vec::new();
         for (name, rustc_file) in rustc_file_map {
             if let Some(gccrs_file) = gccrs_file_map.get(&name) {
-                cases.push(TestCase::new(name, rustc_file, gccrs_file.clone()));
+                cases.push(TestCase::from_pair(name, rustc_file, gccrs_file.clone())?);
             } else {
                 return Err(TestSuiteError::InvalidPath(format!(
                     "No matching file for '{}' in gccrs directory",
@@ -101,6 +202,30 @@ impl TestSuite {
                 )));
             }
         }
+        cases.sort_by(|a, b| a.name.cmp(&b.name));
+        let size = cases.len();
+        Ok(Self { cases, size })
+    }
+
+    /// Loads a single-source test case, or every `.rs` file of a directory
+    /// as one, each of which is built with both compilers.
+    pub fn from_sources(path: &Path) -> Result<Self, TestSuiteError> {
+        let files = if path.is_dir() {
+            rust_files_in_dir(path)?
+        } else if path.is_file() {
+            vec![path.to_path_buf()]
+        } else {
+            let msg = format!("'{}' does not exist", path.display());
+            return Err(TestSuiteError::InvalidPath(msg));
+        };
+
+        let mut cases = Vec::new();
+        for file in files {
+            let name = extract_test_name(&file)
+                .ok_or_else(|| TestSuiteError::InvalidPath("Invalid file name".to_string()))?;
+            cases.push(TestCase::from_source(name, &file)?);
+        }
+        cases.sort_by(|a, b| a.name.cmp(&b.name));
         let size = cases.len();
         Ok(Self { cases, size })
     }
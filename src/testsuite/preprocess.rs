@@ -0,0 +1,150 @@
+use crate::compiler::CompilerKind;
+
+const DIRECTIVE_PREFIX: &str = "//@";
+const COMPILERS: [CompilerKind; 2] = [CompilerKind::Rustc, CompilerKind::Gccrs];
+
+#[derive(Debug)]
+pub struct PreprocessError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Produces the variant of a single-source test case that `compiler` should
+/// build.
+///
+/// A `//@ cfg(<compiler>)` comment on its own line opens a block that is only
+/// kept for that compiler and is closed by `//@ end`. The same comment after
+/// code on a line keeps just that line. Dropped lines are left empty so that
+/// line numbers in diagnostics still match the original file.
+pub fn preprocess(source: &str, compiler: CompilerKind) -> Result<String, PreprocessError> {
+    let mut output = String::with_capacity(source.len());
+    let mut block: Option<(CompilerKind, usize)> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let keep = match line.find(DIRECTIVE_PREFIX) {
+            Some(start) if line[..start].trim().is_empty() => {
+                match line[start + DIRECTIVE_PREFIX.len()..].trim() {
+                    "end" => {
+                        let Some((target, _)) = block.take() else {
+                            return Err(PreprocessError {
+                                line: line_number,
+                                message: "`end` without a matching `cfg(..)` block".to_string(),
+                            });
+                        };
+                        // The closing line belongs to the block it closes.
+                        target == compiler
+                    }
+                    directive => {
+                        if let Some(target) = parse_cfg(directive, line_number)? {
+                            if let Some((_, opened_at)) = block {
+                                return Err(PreprocessError {
+                                    line: line_number,
+                                    message: format!(
+                                        "nested `cfg(..)` block, the block opened at line {} is still open",
+                                        opened_at
+                                    ),
+                                });
+                            }
+                            block = Some((target, line_number));
+                        }
+                        true
+                    }
+                }
+            }
+            Some(start) => {
+                match parse_cfg(line[start + DIRECTIVE_PREFIX.len()..].trim(), line_number)? {
+                    Some(target) => target == compiler,
+                    None => true,
+                }
+            }
+            None => true,
+        };
+
+        let in_block = block.is_none_or(|(target, _)| target == compiler);
+        if keep && in_block {
+            output.push_str(line);
+        }
+        output.push('\n');
+    }
+
+    if let Some((_, opened_at)) = block {
+        return Err(PreprocessError {
+            line: opened_at,
+            message: "`cfg(..)` block is never closed with `//@ end`".to_string(),
+        });
+    }
+    Ok(output)
+}
+
+/// Parses a `cfg(<compiler>)` directive, returning `None` for any other
+/// directive.
+fn parse_cfg(directive: &str, line: usize) -> Result<Option<CompilerKind>, PreprocessError> {
+    let Some(name) = directive
+        .strip_prefix("cfg(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    else {
+        return Ok(None);
+    };
+
+    COMPILERS
+        .into_iter()
+        .find(|kind| kind.to_string() == name.trim())
+        .map(Some)
+        .ok_or_else(|| PreprocessError {
+            line,
+            message: format!("unknown compiler '{}' in `cfg(..)`", name.trim()),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_line(source: &str) -> usize {
+        preprocess(source, CompilerKind::Rustc).unwrap_err().line
+    }
+
+    #[test]
+    fn keeps_blocks_for_their_compiler_only() {
+        let source = "a\n//@ cfg(gccrs)\nb\n//@ end\nc\n";
+        assert_eq!(
+            preprocess(source, CompilerKind::Gccrs).unwrap(),
+            "a\n//@ cfg(gccrs)\nb\n//@ end\nc\n"
+        );
+        assert_eq!(
+            preprocess(source, CompilerKind::Rustc).unwrap(),
+            "a\n\n\n\nc\n"
+        );
+    }
+
+    #[test]
+    fn trailing_directive_keeps_one_line() {
+        let source = "fn main() -> i32 { //@ cfg(gccrs)\nfn main() { //@ cfg(rustc)\n";
+        assert_eq!(
+            preprocess(source, CompilerKind::Rustc).unwrap(),
+            "\nfn main() { //@ cfg(rustc)\n"
+        );
+    }
+
+    #[test]
+    fn preserves_line_numbers() {
+        let source = "//@ cfg(rustc)\nx\ny\n//@ end\nz";
+        let output = preprocess(source, CompilerKind::Gccrs).unwrap();
+        assert_eq!(output.lines().count(), source.lines().count());
+    }
+
+    #[test]
+    fn ignores_other_directives() {
+        let source = "//@ run-pass\nlet x = 1; //@ ignore\n";
+        assert_eq!(preprocess(source, CompilerKind::Rustc).unwrap(), source);
+    }
+
+    #[test]
+    fn rejects_malformed_blocks() {
+        assert_eq!(error_line("a\n//@ end\n"), 2);
+        assert_eq!(error_line("//@ cfg(rustc)\n//@ cfg(gccrs)\n//@ end\n"), 2);
+        assert_eq!(error_line("a\n//@ cfg(rustc)\nb\n"), 2);
+        assert_eq!(error_line("//@ cfg(clang)\n"), 1);
+    }
+}
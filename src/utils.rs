@@ -1,6 +1,9 @@
 use log::info;
 use std::fs::read_dir;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
 pub fn get_files_in_dir(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
     info!("Reading files from directory: {}", dir.display());
@@ -18,3 +21,36 @@ pub fn get_files_in_dir(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
         .collect::<Vec<_>>();
     Ok(files)
 }
+
+/// Applies `f` to every item on up to `jobs` worker threads and returns the
+/// results in the same order as `items`.
+pub fn parallel_map<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+    let workers = jobs.clamp(1, items.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().expect("worker panicked")[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .expect("worker panicked")
+        .into_iter()
+        .map(|result| result.expect("every item is processed exactly once"))
+        .collect()
+}
@@ -0,0 +1,182 @@
+use crate::compiler::{BuildProfile, CompilerKind};
+use crate::testsuite::{CaseSource, TestCase};
+use log::info;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum WorkspaceError {
+    #[error("Failed to prepare build directory '{path}': {error}")]
+    Create {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+
+    #[error("Failed to write '{path}': {error}")]
+    Write {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+
+    #[error("Failed to remove build directory '{path}': {error}")]
+    Remove {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+
+    #[error("Test case name '{0}' cannot be used as a directory name")]
+    InvalidCaseName(String),
+}
+
+/// Root directory under which every test case gets its own build directory.
+pub struct BuildWorkspace {
+    root: PathBuf,
+}
+
+/// Build directory owned by a single test case.
+pub struct CaseWorkspace {
+    dir: PathBuf,
+}
+
+impl BuildWorkspace {
+    pub fn new(root: &Path) -> Result<Self, WorkspaceError> {
+        fs::create_dir_all(root).map_err(|error| WorkspaceError::Create {
+            path: root.to_path_buf(),
+            error,
+        })?;
+        Ok(Self {
+            root: root.to_path_buf(),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Creates a fresh directory for `case`, discarding artifacts left over
+    /// from a previous run. The case name has to be a plain file name, so
+    /// that only directories under the root are ever removed.
+    pub fn for_case(&self, case: &TestCase) -> Result<CaseWorkspace, WorkspaceError> {
+        let mut components = Path::new(&case.name).components();
+        let plain = matches!(components.next(), Some(Component::Normal(_)))
+            && components.next().is_none()
+            && !case.name.contains(['/', '\\']);
+        if !plain {
+            return Err(WorkspaceError::InvalidCaseName(case.name.clone()));
+        }
+
+        let dir = self.root.join(&case.name);
+        if dir.exists() {
+            fs::remove_dir_all(&dir).map_err(|error| WorkspaceError::Remove {
+                path: dir.clone(),
+                error,
+            })?;
+        }
+        fs::create_dir_all(&dir).map_err(|error| WorkspaceError::Create {
+            path: dir.clone(),
+            error,
+        })?;
+        Ok(CaseWorkspace { dir })
+    }
+}
+
+impl CaseWorkspace {
+    /// Returns the file the compiler `name` should build for `case`, writing
+    /// the `dialect` variant of single-source cases into the directory first.
+    pub fn source(
+        &self,
+        case: &TestCase,
+        dialect: CompilerKind,
+        name: &str,
+        prelude: Option<&str>,
+    ) -> Result<PathBuf, WorkspaceError> {
+        let content = match &case.source {
+            CaseSource::Pair { rustc, gccrs } => {
+                return Ok(match dialect {
+                    CompilerKind::Rustc => rustc.clone(),
+                    CompilerKind::Gccrs => gccrs.clone(),
+                })
+            }
+            CaseSource::Single { rustc, gccrs } => match dialect {
+                CompilerKind::Rustc => rustc,
+                CompilerKind::Gccrs => gccrs,
+            },
+        };
+
+        let path = self.dir.join(format!("{}.rs", name));
+        // The prelude goes after the test code, so that line numbers in
+        // diagnostics and panic messages still match the original file.
+        let content = match prelude {
+            Some(prelude) if content.is_empty() || content.ends_with('\n') => {
+                format!("{}{}\n", content, prelude)
+            }
+            Some(prelude) => format!("{}\n{}\n", content, prelude),
+            None => content.clone(),
+        };
+        fs::write(&path, content).map_err(|error| WorkspaceError::Write {
+            path: path.clone(),
+            error,
+        })?;
+        Ok(path)
+    }
+
+    pub fn binary(&self, compiler: &str, profile: &BuildProfile) -> PathBuf {
+        self.dir
+            .join(format!("{}_{}.out", compiler, profile.file_stem()))
+    }
+
+    /// Removes the directory and everything built into it.
+    pub fn cleanup(self) -> Result<(), WorkspaceError> {
+        fs::remove_dir_all(&self.dir).map_err(|error| WorkspaceError::Remove {
+            path: self.dir.clone(),
+            error,
+        })
+    }
+
+    /// Leaves the artifacts on disk for later inspection.
+    pub fn keep(self) {
+        info!("Keeping build artifacts in '{}'", self.dir.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testsuite::{Directives, GoldenFiles};
+
+    fn case(name: &str) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            source: CaseSource::Single {
+                rustc: String::new(),
+                gccrs: String::new(),
+            },
+            directives: Directives::default(),
+            golden: GoldenFiles::next_to(Path::new("case.rs"), name),
+            expected: None,
+        }
+    }
+
+    #[test]
+    fn case_directories_stay_under_the_root() {
+        let root = std::env::temp_dir().join(format!("sev-workspace-{}", std::process::id()));
+        let workspace = BuildWorkspace::new(&root).unwrap();
+
+        for name in ["", ".", "..", "../escape", "a/b", "a\\b", "/tmp"] {
+            assert!(
+                matches!(
+                    workspace.for_case(&case(name)),
+                    Err(WorkspaceError::InvalidCaseName(_))
+                ),
+                "{:?}",
+                name
+            );
+        }
+        let case_workspace = workspace.for_case(&case("fn_pointers..v2")).unwrap();
+        assert!(root.join("fn_pointers..v2").is_dir());
+        case_workspace.cleanup().unwrap();
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
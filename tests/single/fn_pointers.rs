@@ -93,10 +93,11 @@ fn nested_struct_function_pointer_test() {
     }
 }
 
-fn main() -> i32 {
+fn main() -> i32 { //@ cfg(gccrs)
+fn main() { //@ cfg(rustc)
     basic_function_pointer_test();
     multi_level_indirection_test();
     mutability_test();
     nested_struct_function_pointer_test();
-    0
+    0 //@ cfg(gccrs)
 }
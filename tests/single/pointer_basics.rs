@@ -140,11 +140,12 @@ fn nested_pointer_tests() {
 //     }
 // }
 
-fn main() -> i32 {
+fn main() -> i32 { //@ cfg(gccrs)
+fn main() { //@ cfg(rustc)
     derefrence_raw_const_pointers();
     derefrence_raw_mut_pointers();
     pointer_comparisons();
     nested_pointer_tests();
     // pointer_arithmetic();
-    0
+    0 //@ cfg(gccrs)
 }